@@ -0,0 +1,120 @@
+use super::connect::Connection;
+use super::import::ImportExport;
+use super::option;
+use log::trace;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// Opens a full-duplex connection to a git service, e.g. `git-upload-pack`, at
+/// the given URL.
+pub type ConnectFn = Box<dyn Fn(&str, &str) -> anyhow::Result<Connection>>;
+
+/// Capabilities that implementers can opt in to in addition to the ones that
+/// are always supported.
+#[derive(Default)]
+pub struct Capabilities {
+    /// Enables the `connect` capability for transports that are full-duplex.
+    pub connect: Option<ConnectFn>,
+
+    /// Enables the `import` and `export` capabilities for backends that don't
+    /// store git objects, in place of `fetch`, `push` and `stateless-connect`.
+    pub import_export: Option<Box<dyn ImportExport>>,
+
+    /// Advertised as `refspec <refspec>` so that git knows which private
+    /// namespace the remote's refs are written to, e.g.
+    /// `refs/heads/*:refs/icp/origin/heads/*`. Refs are written there by
+    /// `import`, while git updates the refs itself after `fetch`.
+    pub refspecs: Vec<String>,
+
+    /// Advertised as `no-private-update` so that git doesn't update the refs
+    /// in the private namespace after a successful push.
+    pub no_private_update: bool,
+
+    /// Advertised as `*import-marks <file>` so that git loads marks from the
+    /// file before exporting. Relative paths are resolved against the git
+    /// directory.
+    pub import_marks: Option<PathBuf>,
+
+    /// Advertised as `*export-marks <file>` so that git saves marks to the file
+    /// after exporting. Relative paths are resolved against the git directory.
+    pub export_marks: Option<PathBuf>,
+
+    /// Advertised as `signed-tags` so that git exports signed tags verbatim
+    /// instead of stripping their signatures.
+    pub signed_tags: bool,
+}
+
+impl Capabilities {
+    /// The lines to output in response to the `capabilities` command.
+    fn lines(&self, git_dir: Option<&Path>) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if self.connect.is_some() {
+            lines.push("connect".to_string());
+        }
+
+        // Git prefers `fetch` and `push` over `import` and `export` when both
+        // are advertised, and `stateless-connect` talks to a git server which
+        // an import/export backend doesn't have, so only one set is offered.
+        if self.import_export.is_some() {
+            lines.push("export".to_string());
+            lines.push("import".to_string());
+            lines.push("option".to_string());
+        } else {
+            lines.push("fetch".to_string());
+            lines.push("option".to_string());
+            lines.push("push".to_string());
+            lines.push("stateless-connect".to_string());
+        }
+
+        lines.extend(
+            option::CAPABILITIES
+                .iter()
+                .map(|capability| capability.to_string()),
+        );
+
+        lines.extend(
+            self.refspecs
+                .iter()
+                .map(|refspec| format!("refspec {}", refspec)),
+        );
+
+        if self.no_private_update {
+            lines.push("no-private-update".to_string());
+        }
+
+        if self.import_export.is_some() {
+            let resolve = |path: &Path| match git_dir {
+                Some(git_dir) if path.is_relative() => git_dir.join(path),
+                _ => path.to_path_buf(),
+            };
+
+            if let Some(import_marks) = &self.import_marks {
+                lines.push(format!("*import-marks {}", resolve(import_marks).display()));
+            }
+
+            if let Some(export_marks) = &self.export_marks {
+                lines.push(format!("*export-marks {}", resolve(export_marks).display()));
+            }
+
+            if self.signed_tags {
+                lines.push("signed-tags".to_string());
+            }
+        }
+
+        lines
+    }
+}
+
+pub fn execute(capabilities: &Capabilities, git_dir: Option<&Path>) {
+    trace!("capabilities");
+
+    // TODO: buffer and flush
+    capabilities
+        .lines(git_dir)
+        .iter()
+        .for_each(|line| println!("{}", line));
+    println!();
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+#[test]
+fn test_lines_default() {
+    let capabilities = Capabilities::default();
+    assert_eq!(
+        capabilities.lines(None),
+        vec![
+            "fetch",
+            "option",
+            "push",
+            "stateless-connect",
+            "object-format",
+            "check-connectivity",
+        ],
+        "capabilities"
+    )
+}
+
+#[test]
+fn test_lines_refspec() {
+    let capabilities = Capabilities {
+        refspecs: vec![
+            "refs/heads/*:refs/icp/origin/heads/*".to_string(),
+            "refs/tags/*:refs/icp/origin/tags/*".to_string(),
+        ],
+        no_private_update: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        capabilities.lines(None),
+        vec![
+            "fetch",
+            "option",
+            "push",
+            "stateless-connect",
+            "object-format",
+            "check-connectivity",
+            "refspec refs/heads/*:refs/icp/origin/heads/*",
+            "refspec refs/tags/*:refs/icp/origin/tags/*",
+            "no-private-update",
+        ],
+        "capabilities"
+    )
+}
+
+struct NullBackend;
+
+impl ImportExport for NullBackend {
+    fn import(
+        &mut self,
+        _refs: &[git_repository::bstr::BString],
+    ) -> anyhow::Result<Vec<crate::git::fast_import::Command>> {
+        Ok(Vec::new())
+    }
+
+    fn export(
+        &mut self,
+        _commands: Vec<crate::git::fast_import::Command>,
+    ) -> anyhow::Result<Vec<crate::commands::export::RefStatus>> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_lines_import_export() {
+    let capabilities = Capabilities {
+        import_export: Some(Box::new(NullBackend)),
+        refspecs: vec!["refs/heads/*:refs/icp/origin/heads/*".to_string()],
+        import_marks: Some(PathBuf::from("icp/marks")),
+        export_marks: Some(PathBuf::from("/tmp/marks")),
+        signed_tags: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        capabilities.lines(Some(Path::new("/repo/.git"))),
+        vec![
+            "export",
+            "import",
+            "option",
+            "object-format",
+            "check-connectivity",
+            "refspec refs/heads/*:refs/icp/origin/heads/*",
+            "*import-marks /repo/.git/icp/marks",
+            "*export-marks /tmp/marks",
+            "signed-tags",
+        ],
+        "capabilities"
+    )
+}
@@ -0,0 +1,56 @@
+// https://git-scm.com/docs/gitremote-helpers#Documentation/gitremote-helpers.txt-emconnectem
+
+use log::trace;
+use std::io::{Read, Write};
+
+/// The two halves of a full-duplex connection to a git service.
+pub struct Connection {
+    pub reader: Box<dyn Read + Send>,
+    pub writer: Box<dyn Write + Send>,
+}
+
+/// Bridges stdin and stdout to the connection until the server closes it.
+///
+/// Git speaks the protocol of the requested service directly, starting with
+/// reading the server's advertisement.
+pub fn execute(connection: Connection) -> anyhow::Result<()> {
+    let Connection {
+        mut reader,
+        mut writer,
+    } = connection;
+
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    // The connection is established
+    writeln!(stdout)?;
+    stdout.flush()?;
+
+    // Git doesn't close stdin until we exit, so this thread isn't joined.
+    let _ = std::thread::spawn(move || -> std::io::Result<()> {
+        let stdin = std::io::stdin();
+        let mut stdin = stdin.lock();
+        let bytes_written = std::io::copy(&mut stdin, &mut writer)?;
+        trace!("connect: bytes written: {}", bytes_written);
+        writer.flush()
+    });
+
+    // Copied by hand rather than using `std::io::copy` since we need to flush
+    // after each read for git to see the data in time.
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let bytes_read = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(bytes_read) => bytes_read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        stdout.write_all(&buf[..bytes_read])?;
+        stdout.flush()?;
+    }
+
+    trace!("connect: closed by server");
+
+    Ok(())
+}
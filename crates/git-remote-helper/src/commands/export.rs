@@ -0,0 +1,41 @@
+// https://git-scm.com/docs/gitremote-helpers#Documentation/gitremote-helpers.txt-emexportem
+
+use super::import::ImportExport;
+use crate::git::fast_import;
+use git::bstr::BString;
+use git_repository as git;
+use log::trace;
+
+/// The outcome of updating a ref from an export.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RefStatus {
+    Ok(BString),
+    Error(BString, String),
+}
+
+/// Reads a fast-export stream from stdin, applies it to the backend and
+/// reports the status of each ref in the same format as `push`.
+pub fn execute(backend: &mut dyn ImportExport) -> anyhow::Result<()> {
+    trace!("export");
+
+    let stdin = std::io::stdin();
+    let commands = fast_import::read(&mut stdin.lock())?;
+
+    trace!("commands: {:#?}", commands);
+
+    let ref_statuses = backend.export(commands)?;
+
+    ref_statuses.iter().for_each(|ref_status| {
+        let output = match ref_status {
+            RefStatus::Ok(ref_name) => format!("ok {}", ref_name),
+            RefStatus::Error(ref_name, error_msg) => format!("error {} {}", ref_name, error_msg),
+        };
+        trace!("output: {}", output);
+        println!("{}", output);
+    });
+
+    // Terminate the status report output
+    println!();
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+use git::objs::tree::EntryMode;
+use git::prelude::Find as _;
+use git_repository as git;
+use log::trace;
+use std::collections::HashSet;
+
+#[cfg(test)]
+mod tests;
+
+/// Where [`check`] reads objects from.
+pub trait Objects {
+    /// The kind and data of the object, if present.
+    fn find(&self, id: &git::hash::oid) -> anyhow::Result<Option<(git::objs::Kind, Vec<u8>)>>;
+
+    fn contains(&self, id: &git::hash::oid) -> bool;
+
+    /// Whether the object was present before the fetch, in which case the
+    /// objects it references were checked when it arrived.
+    fn existed_before(&self, id: &git::hash::oid) -> bool;
+}
+
+/// The objects of a repository after a fetch.
+pub struct Fetched<'a> {
+    pub repo: &'a git::Repository,
+
+    /// The index of the pack that was received, or `None` if nothing was.
+    pub pack: Option<git::odb::pack::index::File>,
+}
+
+impl Objects for Fetched<'_> {
+    fn find(&self, id: &git::hash::oid) -> anyhow::Result<Option<(git::objs::Kind, Vec<u8>)>> {
+        let object = self.repo.try_find_object(id.to_owned())?;
+        Ok(object.map(|object| (object.kind, object.data.clone())))
+    }
+
+    fn contains(&self, id: &git::hash::oid) -> bool {
+        self.repo.objects.contains(id)
+    }
+
+    fn existed_before(&self, id: &git::hash::oid) -> bool {
+        match &self.pack {
+            Some(pack) => pack.lookup(id).is_none(),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Missing(git::hash::ObjectId),
+    Invalid {
+        id: git::hash::ObjectId,
+        reason: String,
+    },
+    Find(anyhow::Error),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(id) => write!(f, "missing object {} after fetch", id),
+            Self::Invalid { id, reason } => write!(f, "invalid object {}: {}", id, reason),
+            Self::Find(err) => write!(f, "failed to read object: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Checks that every object reachable from `tips` is present, like
+/// `git rev-list --objects` does for git's own connectivity check.
+///
+/// The walk stops at objects that were present before the fetch so that only
+/// the objects that were just received are visited, rather than the entire
+/// history.
+///
+/// With `fsck_objects`, objects are also read and validated in the spirit of
+/// `git fsck`, rather than only checked for existence.
+pub fn check(
+    objects: &impl Objects,
+    tips: impl IntoIterator<Item = git::hash::ObjectId>,
+    fsck_objects: bool,
+) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    let mut pending = tips.into_iter().collect::<Vec<_>>();
+
+    trace!("check connectivity from: {:#?}", pending);
+
+    while let Some(id) = pending.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+
+        if objects.existed_before(&id) {
+            if !objects.contains(&id) {
+                return Err(Error::Missing(id));
+            }
+            continue;
+        }
+
+        let (kind, data) = objects
+            .find(&id)
+            .map_err(Error::Find)?
+            .ok_or(Error::Missing(id))?;
+
+        let invalid = |err: git::objs::decode::Error| Error::Invalid {
+            id,
+            reason: err.to_string(),
+        };
+
+        match kind {
+            git::objs::Kind::Commit => {
+                let commit = git::objs::CommitRef::from_bytes(&data).map_err(invalid)?;
+                pending.push(commit.tree());
+                pending.extend(commit.parents());
+            }
+            git::objs::Kind::Tree => {
+                let tree = git::objs::TreeRef::from_bytes(&data).map_err(invalid)?;
+
+                for entry in tree.entries {
+                    if fsck_objects {
+                        validate_filename(id, entry.filename)?;
+                    }
+
+                    let entry_id = entry.oid.to_owned();
+
+                    match entry.mode {
+                        // Submodule commits live in another repository
+                        EntryMode::Commit => (),
+                        EntryMode::Tree => pending.push(entry_id),
+                        // Blobs have nothing to traverse so unless they are
+                        // validated we only need to know that they exist.
+                        _ if fsck_objects => pending.push(entry_id),
+                        _ => {
+                            if !objects.contains(&entry_id) {
+                                return Err(Error::Missing(entry_id));
+                            }
+                        }
+                    }
+                }
+            }
+            git::objs::Kind::Tag => {
+                let tag = git::objs::TagRef::from_bytes(&data).map_err(invalid)?;
+                pending.push(tag.target());
+            }
+            git::objs::Kind::Blob => (),
+        }
+    }
+
+    trace!("checked {} objects", seen.len());
+
+    Ok(())
+}
+
+/// Rejects tree entries that could be used to escape the working tree or
+/// overwrite repository data when checked out.
+fn validate_filename(
+    tree_id: git::hash::ObjectId,
+    filename: &git::bstr::BStr,
+) -> Result<(), Error> {
+    let is_invalid = filename.is_empty()
+        || filename == "."
+        || filename == ".."
+        || filename.eq_ignore_ascii_case(b".git")
+        || filename.contains(&b'/')
+        || filename.contains(&b'\0');
+
+    if is_invalid {
+        Err(Error::Invalid {
+            id: tree_id,
+            reason: format!("bad filename {:?}", filename),
+        })
+    } else {
+        Ok(())
+    }
+}
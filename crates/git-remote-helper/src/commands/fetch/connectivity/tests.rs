@@ -0,0 +1,92 @@
+use super::*;
+use git::hash::ObjectId;
+use std::collections::HashMap;
+
+const TREE_ID: &[u8] = b"4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+const COMMIT_ID: &[u8] = b"91536083cdb16ef3c29638054642b50a34ea8c25";
+const PARENT_ID: &[u8] = b"7e4c4ba7b7ce2e8b4e8e5a4b9e3e0fd6a6e8d2b1";
+
+fn object_id(hex: &[u8]) -> ObjectId {
+    ObjectId::from_hex(hex).expect("valid hex object id")
+}
+
+#[derive(Default)]
+struct TestObjects {
+    objects: HashMap<ObjectId, (git::objs::Kind, Vec<u8>)>,
+    existing: HashSet<ObjectId>,
+}
+
+impl TestObjects {
+    fn insert(&mut self, id: &[u8], kind: git::objs::Kind, data: &[u8]) {
+        self.objects.insert(object_id(id), (kind, data.to_vec()));
+    }
+}
+
+impl Objects for TestObjects {
+    fn find(&self, id: &git::hash::oid) -> anyhow::Result<Option<(git::objs::Kind, Vec<u8>)>> {
+        Ok(self.objects.get(&id.to_owned()).cloned())
+    }
+
+    fn contains(&self, id: &git::hash::oid) -> bool {
+        self.objects.contains_key(&id.to_owned())
+    }
+
+    fn existed_before(&self, id: &git::hash::oid) -> bool {
+        self.existing.contains(&id.to_owned())
+    }
+}
+
+fn commit(parent: Option<&[u8]>) -> Vec<u8> {
+    let mut commit = format!("tree {}\n", String::from_utf8_lossy(TREE_ID));
+    if let Some(parent) = parent {
+        commit.push_str(&format!("parent {}\n", String::from_utf8_lossy(parent)));
+    }
+    commit.push_str("author A U Thor <author@example.com> 1112911993 -0700\n");
+    commit.push_str("committer C O Mitter <committer@example.com> 1112911993 -0700\n");
+    commit.push_str("\ninitial\n");
+    commit.into_bytes()
+}
+
+#[test]
+fn test_check_connected() {
+    let mut objects = TestObjects::default();
+    objects.insert(TREE_ID, git::objs::Kind::Tree, b"");
+    objects.insert(PARENT_ID, git::objs::Kind::Commit, &commit(None));
+    objects.insert(COMMIT_ID, git::objs::Kind::Commit, &commit(Some(PARENT_ID)));
+
+    assert!(
+        check(&objects, [object_id(COMMIT_ID)], true).is_ok(),
+        "connected"
+    )
+}
+
+#[test]
+fn test_check_missing_parent() {
+    let mut objects = TestObjects::default();
+    objects.insert(TREE_ID, git::objs::Kind::Tree, b"");
+    objects.insert(COMMIT_ID, git::objs::Kind::Commit, &commit(Some(PARENT_ID)));
+
+    assert!(
+        matches!(
+            check(&objects, [object_id(COMMIT_ID)], false),
+            Err(Error::Missing(id)) if id == object_id(PARENT_ID)
+        ),
+        "missing parent"
+    )
+}
+
+#[test]
+fn test_check_stops_at_existing_objects() {
+    // The parent's own history isn't available, which is only fine because
+    // the parent was already present before the fetch.
+    let mut objects = TestObjects::default();
+    objects.insert(TREE_ID, git::objs::Kind::Tree, b"");
+    objects.insert(PARENT_ID, git::objs::Kind::Commit, b"not a commit");
+    objects.insert(COMMIT_ID, git::objs::Kind::Commit, &commit(Some(PARENT_ID)));
+    objects.existing.insert(object_id(PARENT_ID));
+
+    assert!(
+        check(&objects, [object_id(COMMIT_ID)], true).is_ok(),
+        "bounded"
+    )
+}
@@ -0,0 +1,107 @@
+use super::option::Options;
+use crate::git::config::Config;
+use crate::git::object_format;
+use git_repository as git;
+use log::trace;
+use maybe_async::maybe_async;
+use std::collections::BTreeSet;
+
+mod connectivity;
+
+pub type Batch = BTreeSet<(String, String)>;
+
+#[maybe_async]
+pub async fn process<T>(
+    transport: T,
+    repo: &git::Repository,
+    url: &str,
+    options: &Options,
+    batch: &mut Batch,
+) -> anyhow::Result<()>
+where
+    T: git::protocol::transport::client::Transport,
+{
+    if !batch.is_empty() {
+        trace!("process fetch: {:#?}", batch);
+
+        let mut remote = repo.remote_at(url)?;
+
+        for (hash, _name) in batch.iter() {
+            remote = remote.with_refspecs(Some(hash.as_bytes()), git::remote::Direction::Fetch)?;
+        }
+
+        // Implement once option capability is supported
+        let progress = git::progress::Discard;
+
+        let prepare = remote
+            .to_connection_with_transport(transport, progress)
+            .prepare_fetch(git::remote::ref_map::Options {
+                prefix_from_spec_as_filter_on_remote: true,
+                handshake_parameters: vec![],
+                extra_refspecs: vec![],
+            })
+            .await?;
+
+        object_format::ensure_matches(
+            repo.object_hash(),
+            &prepare.ref_map().handshake.capabilities,
+        )?;
+
+        let outcome = prepare.receive(&git::interrupt::IS_INTERRUPTED).await?;
+
+        trace!("outcome: {:#?}", outcome);
+
+        // Objects come from a server we don't necessarily control, so we
+        // verify them before any refs point to them.
+        let fsck_objects = fsck_objects(repo)?;
+
+        if options.check_connectivity || fsck_objects {
+            let tips = batch
+                .iter()
+                .map(|(hash, _name)| git::hash::ObjectId::from_hex(hash.as_bytes()))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // Objects outside of the received pack were present before the
+            // fetch.
+            let pack = match &outcome.status {
+                git::remote::fetch::Status::Change {
+                    write_pack_bundle, ..
+                } => write_pack_bundle
+                    .index_path
+                    .as_ref()
+                    .map(|path| git::odb::pack::index::File::at(path, repo.object_hash()))
+                    .transpose()?,
+                _ => None,
+            };
+
+            connectivity::check(&connectivity::Fetched { repo, pack }, tips, fsck_objects)?;
+
+            if options.check_connectivity {
+                println!("connectivity-ok");
+            }
+        }
+
+        // Git updates the refs itself after `fetch`. The `refspec` capability
+        // only applies to `import`, so nothing is written to the private
+        // namespace here.
+
+        // TODO: delete .keep files by outputting: lock <file>
+        // TODO: determine if gitoxide handles this for us yet
+
+        batch.clear();
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Whether objects should be validated after fetching, according to
+/// `fetch.fsckObjects` or `transfer.fsckObjects`.
+fn fsck_objects(repo: &git::Repository) -> anyhow::Result<bool> {
+    let config = Config::from_repository(repo)?;
+    let fsck = match config.boolean("fetch.fsckObjects")? {
+        Some(fsck) => Some(fsck),
+        None => config.boolean("transfer.fsckObjects")?,
+    };
+    Ok(fsck.unwrap_or(false))
+}
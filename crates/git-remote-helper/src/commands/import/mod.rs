@@ -0,0 +1,101 @@
+// https://git-scm.com/docs/gitremote-helpers#Documentation/gitremote-helpers.txt-emimportemltnamegt
+
+use crate::git::fast_import;
+use git::bstr::BString;
+use git_repository as git;
+use log::trace;
+use std::collections::BTreeSet;
+use std::io::Write as _;
+
+#[cfg(test)]
+mod tests;
+
+pub type Batch = BTreeSet<String>;
+
+/// A storage backend that exchanges history with git using fast-import
+/// streams, for remotes that don't store git objects and packs.
+pub trait ImportExport {
+    /// Produces the fast-import commands that create the history of the given
+    /// refs.
+    ///
+    /// Refs are named as on the remote. If a `refspec` capability is
+    /// advertised they are moved to its private namespace before the commands
+    /// are passed to git.
+    fn import(&mut self, refs: &[BString]) -> anyhow::Result<Vec<fast_import::Command>>;
+
+    /// Applies the commands parsed from a `git fast-export` stream and reports
+    /// the outcome for each ref that was updated.
+    ///
+    /// Marks in the stream are consistent with the `*import-marks` and
+    /// `*export-marks` files, if advertised.
+    fn export(
+        &mut self,
+        commands: Vec<fast_import::Command>,
+    ) -> anyhow::Result<Vec<super::export::RefStatus>>;
+}
+
+pub fn process(
+    backend: &mut dyn ImportExport,
+    refspecs: &[String],
+    batch: &mut Batch,
+) -> anyhow::Result<()> {
+    if !batch.is_empty() {
+        trace!("process import: {:#?}", batch);
+
+        let refs = batch.iter().map(BString::from).collect::<Vec<_>>();
+        let mut commands = backend.import(&refs)?;
+        rename_to_private_refs(&mut commands, refspecs);
+
+        trace!("commands: {:#?}", commands);
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+
+        // Git requires the stream to be terminated with `done`
+        fast_import::Command::Feature("done".into()).write_to(&mut stdout)?;
+        fast_import::write(&mut stdout, &commands)?;
+        if commands.last() != Some(&fast_import::Command::Done) {
+            fast_import::Command::Done.write_to(&mut stdout)?;
+        }
+        stdout.flush()?;
+
+        batch.clear();
+    }
+
+    Ok(())
+}
+
+/// When a `refspec` capability is advertised git expects the imported refs to
+/// be written to the private namespace.
+fn rename_to_private_refs(commands: &mut [fast_import::Command], refspecs: &[String]) {
+    for command in commands.iter_mut() {
+        let ref_name = match command {
+            fast_import::Command::Commit(commit) => &mut commit.ref_name,
+            fast_import::Command::Reset(reset) => &mut reset.ref_name,
+            _ => continue,
+        };
+
+        if let Some(private_ref_name) = private_ref_name(refspecs, &ref_name.to_string()) {
+            trace!("import {} as {}", ref_name, private_ref_name);
+            *ref_name = private_ref_name.into();
+        }
+    }
+}
+
+/// Maps the name of a ref on the remote to its name in the private namespace
+/// using the first refspec that matches it.
+fn private_ref_name(refspecs: &[String], name: &str) -> Option<String> {
+    refspecs.iter().find_map(|refspec| {
+        let refspec = refspec.strip_prefix('+').unwrap_or(refspec);
+        let (src, dst) = refspec.split_once(':')?;
+
+        match (src.split_once('*'), dst.split_once('*')) {
+            (Some((src_prefix, src_suffix)), Some((dst_prefix, dst_suffix))) => {
+                let matched = name.strip_prefix(src_prefix)?.strip_suffix(src_suffix)?;
+                Some(format!("{}{}{}", dst_prefix, matched, dst_suffix))
+            }
+            (None, None) if src == name => Some(dst.to_string()),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,87 @@
+use super::*;
+
+fn refspecs() -> Vec<String> {
+    vec![
+        "refs/heads/*:refs/icp/origin/heads/*".to_string(),
+        "+refs/tags/*:refs/icp/origin/tags/*".to_string(),
+        "HEAD:refs/icp/origin/HEAD".to_string(),
+    ]
+}
+
+#[test]
+fn test_private_ref_name_glob() {
+    assert_eq!(
+        private_ref_name(&refspecs(), "refs/heads/main"),
+        Some("refs/icp/origin/heads/main".to_string()),
+        "private ref name"
+    )
+}
+
+#[test]
+fn test_private_ref_name_forced_glob() {
+    assert_eq!(
+        private_ref_name(&refspecs(), "refs/tags/v1.0.0"),
+        Some("refs/icp/origin/tags/v1.0.0".to_string()),
+        "private ref name"
+    )
+}
+
+#[test]
+fn test_private_ref_name_exact() {
+    assert_eq!(
+        private_ref_name(&refspecs(), "HEAD"),
+        Some("refs/icp/origin/HEAD".to_string()),
+        "private ref name"
+    )
+}
+
+#[test]
+fn test_private_ref_name_no_match() {
+    assert_eq!(
+        private_ref_name(&refspecs(), "refs/notes/commits"),
+        None,
+        "private ref name"
+    )
+}
+
+#[test]
+fn test_private_ref_name_no_refspecs() {
+    assert_eq!(
+        private_ref_name(&[], "refs/heads/main"),
+        None,
+        "private ref name"
+    )
+}
+
+#[test]
+fn test_rename_to_private_refs() {
+    let mut commands = vec![
+        fast_import::Command::Reset(fast_import::Reset {
+            ref_name: "refs/heads/main".into(),
+            from: None,
+        }),
+        fast_import::Command::Reset(fast_import::Reset {
+            ref_name: "refs/notes/commits".into(),
+            from: None,
+        }),
+        fast_import::Command::Done,
+    ];
+
+    rename_to_private_refs(&mut commands, &refspecs());
+
+    assert_eq!(
+        commands,
+        vec![
+            fast_import::Command::Reset(fast_import::Reset {
+                ref_name: "refs/icp/origin/heads/main".into(),
+                from: None,
+            }),
+            fast_import::Command::Reset(fast_import::Reset {
+                ref_name: "refs/notes/commits".into(),
+                from: None,
+            }),
+            fast_import::Command::Done,
+        ],
+        "commands"
+    )
+}
@@ -0,0 +1,281 @@
+use super::option::Options;
+use crate::git::object_format;
+use anyhow::anyhow;
+use clap::ValueEnum;
+use git::bstr::{BString, ByteVec as _};
+use git_repository as git;
+use log::trace;
+use maybe_async::maybe_async;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, ValueEnum)]
+pub enum ListVariant {
+    ForPush,
+}
+
+/// Lists the refs of upload-pack, returning the outcome of the handshake so
+/// that it can be reused by `fetch`.
+#[maybe_async]
+pub async fn execute<AuthFn, T>(
+    mut transport: T,
+    authenticate: AuthFn,
+    ref_prefixes: &[BString],
+    object_hash: Option<git::hash::Kind>,
+    options: &Options,
+) -> anyhow::Result<git::protocol::handshake::Outcome>
+where
+    AuthFn: FnMut(git::credentials::helper::Action) -> git::credentials::protocol::Result,
+    T: git::protocol::transport::client::Transport,
+{
+    trace!("list");
+    trace!("ref_prefixes: {:#?}", ref_prefixes);
+
+    // Implement once option capability is supported
+    let mut progress = git::progress::Discard;
+    let extra_parameters = vec![];
+
+    let mut outcome = git::protocol::fetch::handshake(
+        &mut transport,
+        authenticate,
+        extra_parameters,
+        &mut progress,
+    )
+    .await?;
+
+    trace!(
+        "server protocol version: {:?}",
+        outcome.server_protocol_version
+    );
+
+    let refs = match outcome.server_protocol_version {
+        git::protocol::transport::Protocol::V2 => {
+            ls_refs(
+                &mut transport,
+                &outcome.capabilities,
+                ref_prefixes,
+                object_hash,
+                &mut progress,
+            )
+            .await?
+        }
+        // Servers that only speak protocol v0 or v1 advertise their refs as
+        // part of the handshake.
+        _ => outcome
+            .refs
+            .take()
+            .ok_or_else(|| anyhow!("failed to take remote refs"))?,
+    };
+
+    trace!("refs: {:#?}", refs);
+
+    let object_format = options
+        .object_format
+        .then(|| object_format::advertised(&outcome.capabilities))
+        .transpose()?;
+
+    print(&refs, object_format);
+
+    Ok(outcome)
+}
+
+#[maybe_async]
+async fn ls_refs<T>(
+    mut transport: T,
+    capabilities: &git::protocol::transport::client::Capabilities,
+    ref_prefixes: &[BString],
+    object_hash: Option<git::hash::Kind>,
+    progress: &mut git::progress::Discard,
+) -> anyhow::Result<Vec<git::protocol::handshake::Ref>>
+where
+    T: git::protocol::transport::client::Transport,
+{
+    let refs = git::protocol::ls_refs(
+        &mut transport,
+        capabilities,
+        |capabilities, arguments, features| {
+            // These are part of the default arguments but we rely on them for
+            // symrefs and peeled tags so we make sure they're present.
+            for argument in ["symrefs", "peel"] {
+                if !arguments.iter().any(|a| a == argument) {
+                    arguments.push(argument.into());
+                }
+            }
+
+            // Needed to report where HEAD will point in an empty repository
+            let supports_unborn = capabilities
+                .capability("ls-refs")
+                .and_then(|ls_refs| ls_refs.supports("unborn"))
+                .unwrap_or(false);
+
+            if supports_unborn && !arguments.iter().any(|a| a == "unborn") {
+                arguments.push("unborn".into());
+            }
+
+            // Without a repository we have no object format of our own to
+            // state and leave it to the server.
+            if let Some(object_hash) = object_hash {
+                if object_format::is_advertised(capabilities)
+                    && !features.iter().any(|(name, _)| *name == "object-format")
+                {
+                    let name = object_format::name(object_hash);
+                    features.push(("object-format", Some(name.into())));
+                }
+            }
+
+            arguments.extend(ref_prefixes.iter().map(|prefix| {
+                let mut argument = BString::from("ref-prefix ");
+                argument.push_str(prefix);
+                argument
+            }));
+
+            Ok(git::protocol::ls_refs::Action::Continue)
+        },
+        progress,
+    )
+    .await?;
+
+    Ok(refs)
+}
+
+/// Derives `ref-prefix` values for `ls-refs` from the fetch refspecs of the
+/// configured remote, so that `git fetch origin main` doesn't list every ref.
+///
+/// Returns no prefixes, meaning all refs are listed, when the remote isn't
+/// configured (e.g. a URL was given instead of a name) since we can't know
+/// which refs git will ask for.
+pub fn ref_prefixes(repo: &git::Repository, remote_name: &str) -> Vec<BString> {
+    match repo.find_remote(remote_name) {
+        Ok(remote) => ref_prefixes_from_specs(
+            remote
+                .refspecs(git::remote::Direction::Fetch)
+                .iter()
+                .map(|spec| spec.to_ref()),
+        ),
+        Err(err) => {
+            trace!("not filtering refs by prefix: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+fn ref_prefixes_from_specs<'a>(
+    specs: impl IntoIterator<Item = git::refspec::RefSpecRef<'a>>,
+) -> Vec<BString> {
+    let mut prefixes: Vec<BString> = Vec::new();
+
+    for spec in specs {
+        match spec.prefix() {
+            Some(prefix) => {
+                if !prefixes.iter().any(|p| p == prefix) {
+                    prefixes.push(prefix.to_owned())
+                }
+            }
+            // A spec without a prefix could match any ref so we can't filter
+            // at all.
+            None => return Vec::new(),
+        }
+    }
+
+    // Git needs HEAD to determine the default branch, and tags to follow the
+    // ones pointing into the fetched history.
+    if !prefixes.is_empty() {
+        for prefix in ["HEAD", "refs/tags/"] {
+            if !prefixes.iter().any(|p| p == prefix) {
+                prefixes.push(prefix.into());
+            }
+        }
+    }
+
+    prefixes
+}
+
+/// Lists the refs from a receive-pack advertisement for `list for-push`.
+pub fn execute_for_push(
+    refs: &[git::protocol::handshake::Ref],
+    capabilities: &git::protocol::transport::client::Capabilities,
+    options: &Options,
+) -> anyhow::Result<()> {
+    trace!("list for-push");
+
+    let object_format = options
+        .object_format
+        .then(|| object_format::advertised(capabilities))
+        .transpose()?;
+
+    print(&push_refs(refs), object_format);
+
+    Ok(())
+}
+
+/// `.have` lines describe objects the server already has rather than refs it
+/// would accept, so they are left out.
+fn push_refs(refs: &[git::protocol::handshake::Ref]) -> Vec<git::protocol::handshake::Ref> {
+    refs.iter()
+        .filter(|r| r.unpack().0 != ".have")
+        .cloned()
+        .collect()
+}
+
+fn print(refs: &[git::protocol::handshake::Ref], object_format: Option<git::hash::Kind>) {
+    // TODO: buffer and flush
+    // This is always `sha1` since SHA-256 repositories are rejected until
+    // gitoxide supports them.
+    if let Some(object_format) = object_format {
+        println!(":object-format {}", object_format::name(object_format));
+    }
+    refs.iter()
+        .flat_map(ref_to_lines)
+        .for_each(|line| println!("{}", line));
+    println!();
+}
+
+fn ref_to_lines(r: &git::protocol::handshake::Ref) -> Vec<String> {
+    use git::protocol::handshake::Ref;
+
+    match r {
+        Ref::Peeled {
+            full_ref_name,
+            tag,
+            object,
+        } => {
+            // An annotated tag is listed under its own name with the id of the
+            // tag object, followed by the object it peels to using the same
+            // `^{}` suffix that `git ls-remote` uses.
+            //
+            // 7e4c4ba7b7ce2e8b4e8e5a4b9e3e0fd6a6e8d2b1 refs/tags/v1.0.0
+            // 91536083cdb16ef3c29638054642b50a34ea8c25 refs/tags/v1.0.0^{}
+            vec![
+                format!("{} {}", tag, full_ref_name),
+                format!("{} {}^{{}}", object, full_ref_name),
+            ]
+        }
+        Ref::Direct {
+            full_ref_name,
+            object,
+        } => {
+            // 91536083cdb16ef3c29638054642b50a34ea8c25 refs/heads/main
+            vec![format!("{} {}", object, full_ref_name)]
+        }
+        Ref::Symbolic {
+            full_ref_name,
+            target,
+            object: _,
+        } => {
+            // @refs/heads/main HEAD
+            vec![format!("@{} {}", target, full_ref_name)]
+        }
+        Ref::Unborn {
+            full_ref_name,
+            target,
+        } => {
+            // An unborn ref has no object to report, only the branch it will
+            // point to once it exists. This is what git needs to set up the
+            // default branch when cloning an empty repository.
+            //
+            // @refs/heads/main HEAD
+            vec![format!("@{} {}", target, full_ref_name)]
+        }
+    }
+}
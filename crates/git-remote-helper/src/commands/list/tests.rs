@@ -0,0 +1,133 @@
+use super::*;
+use git::bstr::BString;
+use git::hash::ObjectId;
+use git::protocol::handshake::Ref;
+use git::refspec::parse::Operation;
+use git_repository as git;
+
+const TAG_ID: &[u8] = b"7e4c4ba7b7ce2e8b4e8e5a4b9e3e0fd6a6e8d2b1";
+const COMMIT_ID: &[u8] = b"91536083cdb16ef3c29638054642b50a34ea8c25";
+
+fn object_id(hex: &[u8]) -> ObjectId {
+    ObjectId::from_hex(hex).expect("valid hex object id")
+}
+
+#[test]
+fn test_ref_to_lines_peeled() {
+    let r = Ref::Peeled {
+        full_ref_name: BString::from("refs/tags/v1.0.0"),
+        tag: object_id(TAG_ID),
+        object: object_id(COMMIT_ID),
+    };
+    assert_eq!(
+        ref_to_lines(&r),
+        vec![
+            "7e4c4ba7b7ce2e8b4e8e5a4b9e3e0fd6a6e8d2b1 refs/tags/v1.0.0".to_string(),
+            "91536083cdb16ef3c29638054642b50a34ea8c25 refs/tags/v1.0.0^{}".to_string(),
+        ],
+        "peeled"
+    )
+}
+
+#[test]
+fn test_ref_to_lines_direct() {
+    let r = Ref::Direct {
+        full_ref_name: BString::from("refs/heads/main"),
+        object: object_id(COMMIT_ID),
+    };
+    assert_eq!(
+        ref_to_lines(&r),
+        vec!["91536083cdb16ef3c29638054642b50a34ea8c25 refs/heads/main".to_string()],
+        "direct"
+    )
+}
+
+#[test]
+fn test_ref_to_lines_symbolic() {
+    let r = Ref::Symbolic {
+        full_ref_name: BString::from("HEAD"),
+        target: BString::from("refs/heads/main"),
+        object: object_id(COMMIT_ID),
+    };
+    assert_eq!(
+        ref_to_lines(&r),
+        vec!["@refs/heads/main HEAD".to_string()],
+        "symbolic"
+    )
+}
+
+#[test]
+fn test_ref_to_lines_unborn() {
+    let r = Ref::Unborn {
+        full_ref_name: BString::from("HEAD"),
+        target: BString::from("refs/heads/main"),
+    };
+    assert_eq!(
+        ref_to_lines(&r),
+        vec!["@refs/heads/main HEAD".to_string()],
+        "unborn"
+    )
+}
+
+#[test]
+fn test_push_refs_drops_have_lines() {
+    let main = Ref::Direct {
+        full_ref_name: BString::from("refs/heads/main"),
+        object: object_id(COMMIT_ID),
+    };
+    let have = Ref::Direct {
+        full_ref_name: BString::from(".have"),
+        object: object_id(TAG_ID),
+    };
+    assert_eq!(
+        push_refs(&[have.clone(), main.clone(), have]),
+        vec![main],
+        "refs"
+    )
+}
+
+fn fetch_spec(spec: &str) -> git::refspec::RefSpecRef<'_> {
+    git::refspec::parse(spec.into(), Operation::Fetch).expect("valid fetch refspec")
+}
+
+#[test]
+fn test_ref_prefixes_from_specs_default_remote() {
+    let specs = vec![fetch_spec("+refs/heads/*:refs/remotes/origin/*")];
+    assert_eq!(
+        ref_prefixes_from_specs(specs),
+        vec![
+            BString::from("refs/heads/"),
+            BString::from("HEAD"),
+            BString::from("refs/tags/"),
+        ],
+        "ref prefixes"
+    )
+}
+
+#[test]
+fn test_ref_prefixes_from_specs_deduplicated() {
+    let specs = vec![
+        fetch_spec("refs/heads/main:refs/remotes/origin/main"),
+        fetch_spec("refs/heads/main:refs/remotes/upstream/main"),
+        fetch_spec("refs/tags/*:refs/tags/*"),
+    ];
+    assert_eq!(
+        ref_prefixes_from_specs(specs),
+        vec![
+            BString::from("refs/heads/main"),
+            BString::from("refs/tags/"),
+            BString::from("HEAD"),
+        ],
+        "ref prefixes"
+    )
+}
+
+#[test]
+fn test_ref_prefixes_from_specs_none() {
+    let specs: Vec<git::refspec::RefSpecRef<'_>> = Vec::new();
+    assert_eq!(
+        ref_prefixes_from_specs(specs),
+        Vec::<BString>::new(),
+        "ref prefixes"
+    )
+}
@@ -1,9 +1,18 @@
-use clap::Parser;
-use strum::EnumVariantNames;
+use clap::{Command, FromArgMatches as _, Parser, Subcommand as _};
+use strum::{EnumVariantNames, VariantNames as _};
 
+pub mod capabilities;
+pub mod connect;
+pub mod export;
 pub mod fetch;
+pub mod import;
 pub mod list;
+pub mod option;
 pub mod push;
+pub mod stateless_connect;
+
+#[cfg(test)]
+mod tests;
 
 use list::ListVariant;
 
@@ -11,15 +20,80 @@ use list::ListVariant;
 #[strum(serialize_all = "kebab_case")]
 pub enum Commands {
     Capabilities,
+    Connect {
+        service: String,
+    },
+    Export,
     Fetch {
         hash: String, // TODO: gitoxide::hash::ObjectId?
 
         name: String,
     },
+    Import {
+        name: String,
+    },
     List {
         variant: Option<ListVariant>,
     },
+    Option {
+        name: String,
+
+        // Joined with spaces since some values, like `push-option`, may
+        // contain them.
+        value: Vec<String>,
+    },
     Push {
         src_dst: String,
     },
+    StatelessConnect {
+        service: String,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    Malformed(String),
+    Unknown(String),
+}
+
+impl ParseError {
+    /// Whether the protocol lets us answer with `unsupported` and carry on.
+    ///
+    /// That's only the case for `option`, everything else expects a specific
+    /// response which we can't give.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, Self::Malformed(line) if line.split(' ').next() == Some("option"))
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            Self::Malformed(line) => format!("malformed command: '{}'", line),
+            Self::Unknown(name) => format!("unknown command: '{}'", name),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a single, non-empty line sent by git.
+pub fn parse(line: &str) -> Result<Commands, ParseError> {
+    let input = line.split(' ').collect::<Vec<_>>();
+
+    let name = input[0];
+    if !Commands::VARIANTS.contains(&name) {
+        return Err(ParseError::Unknown(name.to_owned()));
+    }
+
+    let command = Command::new("git-remote-helper")
+        .multicall(true)
+        .subcommand_required(true);
+    let command = Commands::augment_subcommands(command);
+
+    command
+        .try_get_matches_from(input)
+        .and_then(|matches| Commands::from_arg_matches(&matches))
+        .map_err(|_| ParseError::Malformed(line.to_owned()))
 }
@@ -0,0 +1,61 @@
+use log::trace;
+
+/// Options set by git using the `option` command.
+///
+/// https://git-scm.com/docs/gitremote-helpers#_options
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Options {
+    /// Whether git wants `list` to report the object format with a
+    /// `:object-format` line.
+    pub object_format: bool,
+
+    /// Whether git relies on us to verify that fetched history is complete
+    /// instead of checking it itself.
+    pub check_connectivity: bool,
+}
+
+/// Capabilities that are advertised in addition to the supported commands.
+pub const CAPABILITIES: &[&str] = &["object-format", "check-connectivity"];
+
+pub fn execute(options: &mut Options, name: &str, value: &str) {
+    trace!("option {} {}", name, value);
+
+    let output = match name {
+        "object-format" => set_bool(&mut options.object_format, value),
+        "check-connectivity" => set_bool(&mut options.check_connectivity, value),
+        _ => Output::Unsupported,
+    };
+
+    trace!("output: {}", output);
+    println!("{}", output);
+}
+
+enum Output {
+    Ok,
+    Unsupported,
+    Error(String),
+}
+
+impl std::fmt::Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::Unsupported => write!(f, "unsupported"),
+            Self::Error(msg) => write!(f, "error {}", msg),
+        }
+    }
+}
+
+fn set_bool(option: &mut bool, value: &str) -> Output {
+    match value {
+        "true" => {
+            *option = true;
+            Output::Ok
+        }
+        "false" => {
+            *option = false;
+            Output::Ok
+        }
+        _ => Output::Error(format!("invalid boolean value: {}", value)),
+    }
+}
@@ -1,4 +1,6 @@
+use crate::git::object_format;
 use crate::git::service::receive_pack;
+use crate::interrupt;
 use anyhow::anyhow;
 use git::bstr::ByteSlice as _;
 use git::odb::pack::data::output::count::objects::ObjectExpansion;
@@ -13,17 +15,63 @@ use std::io::Write as _;
 #[cfg(feature = "async-network-client")]
 use git::protocol::futures_lite::io::AsyncWriteExt as _;
 
+#[cfg(test)]
+mod tests;
+
 pub type Batch = BTreeSet<String>;
 
+/// A receive-pack ref advertisement together with the transport it was
+/// received on.
+///
+/// This is obtained once per session so that `list for-push` and the following
+/// `push` batch don't have to perform the handshake twice.
+pub struct Advertisement<T> {
+    pub transport: T,
+    pub refs: Vec<git::protocol::handshake::Ref>,
+    pub capabilities: git::protocol::transport::client::Capabilities,
+}
+
 #[maybe_async]
-pub async fn process<AuthFn, T>(
+pub async fn handshake<AuthFn, T>(
     mut transport: T,
-    repo: &git::Repository,
     authenticate: AuthFn,
+) -> anyhow::Result<Advertisement<T>>
+where
+    AuthFn: FnMut(git::credentials::helper::Action) -> git::credentials::protocol::Result,
+    T: git::protocol::transport::client::Transport,
+{
+    // Implement once option capability is supported
+    let mut progress = git::progress::Discard;
+    let extra_parameters = vec![];
+
+    let mut outcome = git::protocol::handshake(
+        &mut transport,
+        git::protocol::transport::Service::ReceivePack,
+        authenticate,
+        extra_parameters,
+        &mut progress,
+    )
+    .await?;
+
+    let refs = outcome
+        .refs
+        .take()
+        .ok_or_else(|| anyhow!("failed to take remote refs"))?;
+
+    Ok(Advertisement {
+        transport,
+        refs,
+        capabilities: outcome.capabilities,
+    })
+}
+
+#[maybe_async]
+pub async fn process<T>(
+    advertisement: Advertisement<T>,
+    repo: &git::Repository,
     batch: &mut Batch,
 ) -> anyhow::Result<()>
 where
-    AuthFn: FnMut(git::credentials::helper::Action) -> git::credentials::protocol::Result,
     T: git::protocol::transport::client::Transport,
 {
     if !batch.is_empty() {
@@ -32,25 +80,22 @@ where
         use git::refspec::parse::Operation;
         use git::refspec::{instruction, Instruction};
 
-        // Implement once option capability is supported
-        let mut progress = git::progress::Discard;
-        let extra_parameters = vec![];
+        let Advertisement {
+            mut transport,
+            refs: remote_refs,
+            capabilities,
+        } = advertisement;
 
-        let mut outcome = git::protocol::handshake(
-            &mut transport,
-            git::protocol::transport::Service::ReceivePack,
-            authenticate,
-            extra_parameters,
-            &mut progress,
-        )
-        .await?;
+        trace!("remote_refs: {:#?}", remote_refs);
 
-        let remote_refs = outcome
-            .refs
-            .take()
-            .ok_or_else(|| anyhow!("failed to take remote refs"))?;
+        let object_hash = repo.object_hash();
+        object_format::ensure_matches(object_hash, &capabilities)?;
 
-        trace!("remote_refs: {:#?}", remote_refs);
+        let object_format_capability = if object_format::is_advertised(&capabilities) {
+            format!(" object-format={}", object_format::name(object_hash))
+        } else {
+            String::new()
+        };
 
         let mut request_writer = transport.request(
             git::protocol::transport::client::WriteMode::Binary,
@@ -69,6 +114,8 @@ where
 
         trace!("instructions: {:#?}", instructions);
 
+        let dsts = destinations(&instructions);
+
         let push_instructions = instructions
             .iter()
             .filter_map(|instruction| match instruction {
@@ -100,7 +147,7 @@ where
                     (name == *dst).then(|| peeled.or(target)).flatten()
                 })
                 .map(|x| x.to_owned())
-                .unwrap_or_else(|| git::hash::Kind::Sha1.null());
+                .unwrap_or_else(|| object_hash.null());
 
             trace!("dst_id: {:#?}", dst_id);
 
@@ -164,7 +211,8 @@ where
                     git::progress::Discard,
                     &git::interrupt::IS_INTERRUPTED,
                     input_object_expansion,
-                )?;
+                )
+                .map_err(|err| interrupted_or(&dsts, err))?;
 
             counts.shrink_to_fit();
 
@@ -199,10 +247,11 @@ where
             // status report to stdout in the format that remote helpers are
             // expected to produce.
             let chunk = format!(
-                "{} {} {}\0 report-status report-status-v2 \n",
+                "{} {} {}\0 report-status report-status-v2{} \n",
                 dst_id.to_hex(),
                 src_id.to_hex(),
-                dst
+                dst,
+                object_format_capability
             );
 
             request_writer.write_all(chunk.as_bytes().as_bstr()).await?;
@@ -233,12 +282,18 @@ where
             &mut writer,
             num_entries,
             git::odb::pack::data::Version::V2,
-            git::hash::Kind::Sha1,
+            object_hash,
         );
 
         // The pack writer is lazy, so we need to consume it
         for write_result in pack_writer {
-            let bytes_written = write_result?;
+            // Stop writing so that the server receives an incomplete pack,
+            // which it rejects.
+            if interrupt::is_interrupted() {
+                return Err(interrupted(&dsts));
+            }
+
+            let bytes_written = write_result.map_err(|err| interrupted_or(&dsts, err))?;
             trace!("bytes written: {:#?}", bytes_written);
         }
 
@@ -247,9 +302,9 @@ where
 
         trace!("finished writing pack");
 
-
-        let (_unpack_result, command_statuses) =
-            receive_pack::response::read_and_parse(reader).await?;
+        let (_unpack_result, command_statuses) = receive_pack::response::read_and_parse(reader)
+            .await
+            .map_err(|err| interrupted_or(&dsts, err))?;
 
         command_statuses.iter().for_each(|command_status| {
             trace!("{:#?}", command_status);
@@ -275,3 +330,52 @@ where
 
     Ok(())
 }
+
+/// The remote refs that git expects a status for, including the ones that are
+/// deleted.
+fn destinations(instructions: &[git::refspec::Instruction<'_>]) -> Vec<git::bstr::BString> {
+    use git::refspec::{instruction, Instruction};
+
+    instructions
+        .iter()
+        .filter_map(|instruction| match instruction {
+            Instruction::Push(instruction::Push::Matching { dst, .. }) => Some((*dst).to_owned()),
+            Instruction::Push(instruction::Push::Delete { ref_or_pattern }) => {
+                Some((*ref_or_pattern).to_owned())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Reports every ref in the batch as failed since we can't know whether the
+/// server applied the update before we were interrupted.
+fn interrupted(dsts: &[git::bstr::BString]) -> anyhow::Error {
+    if let Err(err) = write_interrupted(&mut std::io::stdout(), dsts) {
+        return err.into();
+    }
+
+    interrupt::Interrupted.into()
+}
+
+fn write_interrupted(
+    out: &mut impl std::io::Write,
+    dsts: &[git::bstr::BString],
+) -> std::io::Result<()> {
+    for dst in dsts {
+        let output = format!("error {} interrupted, state of remote ref is unknown", dst);
+        trace!("output: {}", output);
+        writeln!(out, "{}", output)?;
+    }
+
+    // Terminate the status report output
+    writeln!(out)
+}
+
+fn interrupted_or(dsts: &[git::bstr::BString], err: impl Into<anyhow::Error>) -> anyhow::Error {
+    if interrupt::is_interrupted() {
+        interrupted(dsts)
+    } else {
+        err.into()
+    }
+}
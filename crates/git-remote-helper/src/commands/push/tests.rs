@@ -0,0 +1,32 @@
+use super::*;
+use git::refspec::parse::Operation;
+
+#[test]
+fn test_write_interrupted() {
+    let specs = ["refs/heads/main:refs/heads/main", ":refs/heads/old"];
+    let instructions = specs
+        .iter()
+        .map(|spec| {
+            git::refspec::parse(spec.as_bytes().as_bstr(), Operation::Push)
+                .expect("valid refspec")
+                .instruction()
+        })
+        .collect::<Vec<_>>();
+
+    let dsts = destinations(&instructions);
+    assert_eq!(
+        dsts,
+        vec!["refs/heads/main", "refs/heads/old"],
+        "destinations"
+    );
+
+    let mut out = Vec::new();
+    write_interrupted(&mut out, &dsts).expect("write to vec");
+    assert_eq!(
+        out.as_bstr(),
+        "error refs/heads/main interrupted, state of remote ref is unknown\n\
+         error refs/heads/old interrupted, state of remote ref is unknown\n\
+         \n",
+        "status report"
+    );
+}
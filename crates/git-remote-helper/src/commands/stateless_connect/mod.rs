@@ -0,0 +1,212 @@
+// https://git-scm.com/docs/gitremote-helpers#Documentation/gitremote-helpers.txt-emstateless-connectem
+// https://git-scm.com/docs/gitprotocol-http#_smart_service_git_upload_pack
+
+use git::protocol::transport::packetline::PacketLineRef;
+use git_repository as git;
+use log::trace;
+use maybe_async::maybe_async;
+use std::io::{Read, Write};
+
+#[cfg(feature = "async-network-client")]
+use git::protocol::futures_lite::io::AsyncWriteExt as _;
+
+#[cfg(test)]
+mod tests;
+
+/// The only service that git uses `stateless-connect` for.
+pub const UPLOAD_PACK: &str = "git-upload-pack";
+
+pub enum Outcome {
+    /// The server doesn't speak protocol v2 so git should fall back to using
+    /// `list` and `fetch`.
+    Fallback,
+    /// Git closed the connection after its last request.
+    Closed,
+}
+
+/// Tells git to fall back to the other capabilities for this service.
+pub fn fallback() {
+    trace!("fallback");
+    println!("fallback");
+}
+
+/// Tunnels protocol v2 requests from git to the server.
+///
+/// Each request that git writes to stdin is terminated by a flush packet and
+/// sent to the server verbatim. The response is written to stdout, followed by
+/// a response-end packet so that git knows the response is complete.
+#[maybe_async]
+pub async fn execute<AuthFn, T>(mut transport: T, authenticate: AuthFn) -> anyhow::Result<Outcome>
+where
+    AuthFn: FnMut(git::credentials::helper::Action) -> git::credentials::protocol::Result,
+    T: git::protocol::transport::client::Transport,
+{
+    trace!("stateless-connect {}", UPLOAD_PACK);
+
+    // Implement once option capability is supported
+    let mut progress = git::progress::Discard;
+    let extra_parameters = vec![];
+
+    let outcome = git::protocol::handshake(
+        &mut transport,
+        git::protocol::transport::Service::UploadPack,
+        authenticate,
+        extra_parameters,
+        &mut progress,
+    )
+    .await?;
+
+    trace!(
+        "server protocol version: {:?}",
+        outcome.server_protocol_version
+    );
+
+    if outcome.server_protocol_version != git::protocol::transport::Protocol::V2 {
+        fallback();
+        return Ok(Outcome::Fallback);
+    }
+
+    let stdin = std::io::stdin();
+    let mut stdin = stdin.lock();
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    // The connection is established
+    writeln!(stdout)?;
+
+    // Replay the capability advertisement we received during the handshake
+    write_packet_line(&mut stdout, PacketLineRef::Data(b"version 2\n"))?;
+    for capability in outcome.capabilities.iter() {
+        let mut line = capability.name().to_vec();
+        if let Some(value) = capability.value() {
+            line.push(b'=');
+            line.extend_from_slice(value);
+        }
+        line.push(b'\n');
+        write_packet_line(&mut stdout, PacketLineRef::Data(&line))?;
+    }
+    write_packet_line(&mut stdout, PacketLineRef::Flush)?;
+    stdout.flush()?;
+
+    let mut request = Vec::new();
+
+    loop {
+        request.clear();
+
+        if !read_request(&mut stdin, &mut request)? {
+            trace!("stateless-connect closed");
+            break Ok(Outcome::Closed);
+        }
+
+        trace!("request: {:?}", git::bstr::BStr::new(&request));
+
+        let request_writer = transport.request(
+            git::protocol::transport::client::WriteMode::Binary,
+            // This is currently redundant because we use `.into_parts()`
+            git::protocol::transport::client::MessageKind::Flush,
+        )?;
+
+        let (mut writer, mut reader) = request_writer.into_parts();
+
+        // The request already includes its flush packet
+        writer.write_all(&request).await?;
+
+        // Signal that we are done writing
+        drop(writer);
+
+        while let Some(line) = reader.readline().await {
+            let line = line??;
+            write_packet_line(&mut stdout, line)?;
+        }
+
+        // The reader stops at the flush packet that ends the response without
+        // returning it.
+        write_packet_line(&mut stdout, PacketLineRef::Flush)?;
+        write_packet_line(&mut stdout, PacketLineRef::ResponseEnd)?;
+        stdout.flush()?;
+    }
+}
+
+/// Reads packet lines, including their length prefixes, up to and including
+/// the flush packet that terminates a request.
+///
+/// Returns `false` if the input ended before a request was started.
+fn read_request(reader: &mut impl Read, buf: &mut Vec<u8>) -> std::io::Result<bool> {
+    loop {
+        match read_packet_line(reader, buf)? {
+            Some(PacketKind::Flush) => break Ok(true),
+            Some(_) => (),
+            None if buf.is_empty() => break Ok(false),
+            None => {
+                break Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "request ended without a flush packet",
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum PacketKind {
+    Data,
+    Flush,
+    Delimiter,
+    ResponseEnd,
+}
+
+/// Reads a single packet line and appends it to `buf` as is.
+///
+/// Returns `None` if the input has ended.
+fn read_packet_line(
+    reader: &mut impl Read,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<PacketKind>> {
+    let mut hex_len = [0u8; 4];
+
+    match reader.read_exact(&mut hex_len) {
+        Ok(()) => (),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let len = std::str::from_utf8(&hex_len)
+        .ok()
+        .and_then(|hex_len| usize::from_str_radix(hex_len, 16).ok())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid packet line length: {:?}", hex_len),
+            )
+        })?;
+
+    buf.extend_from_slice(&hex_len);
+
+    match len {
+        0 => Ok(Some(PacketKind::Flush)),
+        1 => Ok(Some(PacketKind::Delimiter)),
+        2 => Ok(Some(PacketKind::ResponseEnd)),
+        3 => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid packet line length: 3",
+        )),
+        _ => {
+            let start = buf.len();
+            buf.resize(start + len - 4, 0);
+            reader.read_exact(&mut buf[start..])?;
+            Ok(Some(PacketKind::Data))
+        }
+    }
+}
+
+fn write_packet_line(writer: &mut impl Write, line: PacketLineRef<'_>) -> std::io::Result<()> {
+    match line {
+        PacketLineRef::Data(data) => {
+            write!(writer, "{:04x}", data.len() + 4)?;
+            writer.write_all(data)
+        }
+        PacketLineRef::Flush => writer.write_all(b"0000"),
+        PacketLineRef::Delimiter => writer.write_all(b"0001"),
+        PacketLineRef::ResponseEnd => writer.write_all(b"0002"),
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+#[test]
+fn test_read_request() {
+    let input = b"0014command=ls-refs\n00010009peel\n0000".to_vec();
+    let mut reader = input.as_slice();
+    let mut buf = Vec::new();
+    let result = read_request(&mut reader, &mut buf);
+    assert_eq!(result.ok(), Some(true), "request");
+    assert_eq!(buf, input, "request");
+}
+
+#[test]
+fn test_read_request_two_requests() {
+    let input = b"0014command=ls-refs\n00000012command=fetch\n0000".to_vec();
+    let mut reader = input.as_slice();
+    let mut buf = Vec::new();
+
+    let result = read_request(&mut reader, &mut buf);
+    assert_eq!(result.ok(), Some(true), "first request");
+    assert_eq!(buf, b"0014command=ls-refs\n0000".to_vec(), "first request");
+
+    buf.clear();
+    let result = read_request(&mut reader, &mut buf);
+    assert_eq!(result.ok(), Some(true), "second request");
+    assert_eq!(buf, b"0012command=fetch\n0000".to_vec(), "second request");
+
+    buf.clear();
+    let result = read_request(&mut reader, &mut buf);
+    assert_eq!(result.ok(), Some(false), "end of input");
+}
+
+#[test]
+fn test_read_request_without_flush() {
+    let input = b"0014command=ls-refs\n".to_vec();
+    let mut reader = input.as_slice();
+    let mut buf = Vec::new();
+    let result = read_request(&mut reader, &mut buf);
+    assert!(result.is_err(), "request");
+}
+
+#[test]
+fn test_read_packet_line_invalid_length() {
+    let input = b"zzzz".to_vec();
+    let mut reader = input.as_slice();
+    let mut buf = Vec::new();
+    let result = read_packet_line(&mut reader, &mut buf);
+    assert!(result.is_err(), "packet line");
+}
+
+#[test]
+fn test_write_packet_line() {
+    let mut output = Vec::new();
+    write_packet_line(&mut output, PacketLineRef::Data(b"version 2\n")).unwrap();
+    write_packet_line(&mut output, PacketLineRef::Delimiter).unwrap();
+    write_packet_line(&mut output, PacketLineRef::Flush).unwrap();
+    write_packet_line(&mut output, PacketLineRef::ResponseEnd).unwrap();
+    assert_eq!(
+        output,
+        b"000eversion 2\n000100000002".to_vec(),
+        "packet lines"
+    );
+}
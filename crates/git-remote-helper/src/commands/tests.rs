@@ -0,0 +1,49 @@
+use super::*;
+
+#[test]
+fn test_parse_command() {
+    assert_eq!(
+        parse("fetch 91536083cdb16ef3c29638054642b50a34ea8c25 refs/heads/main"),
+        Ok(Commands::Fetch {
+            hash: "91536083cdb16ef3c29638054642b50a34ea8c25".into(),
+            name: "refs/heads/main".into(),
+        }),
+        "fetch"
+    );
+    assert_eq!(
+        parse("option push-option a b"),
+        Ok(Commands::Option {
+            name: "push-option".into(),
+            value: vec!["a".into(), "b".into()],
+        }),
+        "option"
+    );
+}
+
+#[test]
+fn test_parse_unknown_command() {
+    let result = parse("get https://example.com/file path");
+    assert_eq!(result, Err(ParseError::Unknown("get".into())), "command");
+    assert!(!result.unwrap_err().is_recoverable(), "recoverable");
+}
+
+#[test]
+fn test_parse_malformed_command() {
+    let result = parse("fetch 91536083cdb16ef3c29638054642b50a34ea8c25");
+    assert_eq!(
+        result,
+        Err(ParseError::Malformed(
+            "fetch 91536083cdb16ef3c29638054642b50a34ea8c25".into()
+        )),
+        "fetch"
+    );
+    assert!(!result.unwrap_err().is_recoverable(), "recoverable");
+
+    let result = parse("option");
+    assert_eq!(
+        result,
+        Err(ParseError::Malformed("option".into())),
+        "option"
+    );
+    assert!(result.unwrap_err().is_recoverable(), "recoverable");
+}
@@ -1,23 +1,319 @@
-use anyhow::anyhow;
+//! Reading git configuration in-process.
+//!
+//! The configuration files are read via gitoxide, which doesn't know about
+//! values passed with `git -c <key>=<value>` or `git --config-env`. Git
+//! forwards those to us using `GIT_CONFIG_PARAMETERS` and
+//! `GIT_CONFIG_COUNT`, so we layer them on top ourselves.
 
-// TODO: figure out why this doesn't find the setting when used with `git -c`
-// let private_key_path = config.string("icp.privateKey").ok_or_else(|| {
-//     anyhow!("failed to read icp.privateKey from git config. Set with `git config --global icp.privateKey <path to private key>`")
-// })?;
+use anyhow::{anyhow, Context as _};
+use git::bstr::ByteSlice as _;
+use git_repository as git;
+use log::trace;
+use std::env;
+use std::path::PathBuf;
 
-pub fn get(key: &str) -> anyhow::Result<String> {
-    let config_value = std::process::Command::new("git")
-        .arg("config")
-        .arg(key)
-        .output()?;
+#[cfg(test)]
+mod tests;
 
-    let config_value = config_value.stdout;
-    let config_value = String::from_utf8(config_value)?;
-    let config_value = config_value.trim().to_string();
+const GIT_CONFIG_COUNT: &str = "GIT_CONFIG_COUNT";
+const GIT_CONFIG_KEY_PREFIX: &str = "GIT_CONFIG_KEY_";
+const GIT_CONFIG_PARAMETERS: &str = "GIT_CONFIG_PARAMETERS";
+const GIT_CONFIG_VALUE_PREFIX: &str = "GIT_CONFIG_VALUE_";
+const GIT_DIR: &str = "GIT_DIR";
 
-    if config_value.is_empty() {
-        Err(anyhow!("{} is empty", key))
+/// A key and its value, where a missing value means the key was given
+/// without `=`, which git treats as `true`.
+type Entry = (String, Option<String>);
+
+pub struct Config {
+    file: git::config::File<'static>,
+
+    // In the order git applies them, so later entries win.
+    overrides: Vec<Entry>,
+}
+
+impl Config {
+    /// Reads the configuration of the repository in `GIT_DIR`, or only the
+    /// global configuration if it isn't set.
+    pub fn open() -> anyhow::Result<Self> {
+        match env::var_os(GIT_DIR) {
+            Some(git_dir) => {
+                let repo = git::open(git_dir).context("failed to open GIT_DIR")?;
+                Self::from_repository(&repo)
+            }
+            None => {
+                let file = git::config::File::from_globals()
+                    .context("failed to read global git config")?;
+                Self::with_file(file)
+            }
+        }
+    }
+
+    pub fn from_repository(repo: &git::Repository) -> anyhow::Result<Self> {
+        Self::with_file(repo.config_snapshot().plumbing().clone())
+    }
+
+    fn with_file(file: git::config::File<'static>) -> anyhow::Result<Self> {
+        let mut overrides = overrides_from_count()?;
+
+        if let Some(parameters) = env::var_os(GIT_CONFIG_PARAMETERS) {
+            let parameters = parameters
+                .into_string()
+                .map_err(|_| anyhow!("{} is not valid UTF-8", GIT_CONFIG_PARAMETERS))?;
+            overrides.extend(parse_parameters(&parameters)?);
+        }
+
+        trace!("config overrides: {:#?}", overrides);
+
+        Ok(Self { file, overrides })
+    }
+
+    /// The value of `key`, where `Some(None)` means the key is present
+    /// without a value.
+    fn raw(&self, key: &str) -> Option<Option<String>> {
+        let normalized = normalize_key(key);
+
+        let value = self
+            .overrides
+            .iter()
+            .rev()
+            .find(|(name, _)| normalize_key(name) == normalized)
+            .map(|(_, value)| value.clone());
+
+        value.or_else(|| {
+            let value = self.file.string_by_key(key)?;
+
+            // A key without a value reads as an empty string, just like one
+            // set to an empty string, but only the former is `true`.
+            if value.is_empty() && matches!(self.file.boolean_by_key(key), Some(Ok(true))) {
+                Some(None)
+            } else {
+                Some(Some(value.to_str_lossy().into_owned()))
+            }
+        })
+    }
+
+    pub fn string(&self, key: &str) -> Option<String> {
+        self.raw(key).map(Option::unwrap_or_default)
+    }
+
+    pub fn boolean(&self, key: &str) -> anyhow::Result<Option<bool>> {
+        self.raw(key)
+            .map(|value| match value {
+                Some(value) => parse_boolean(&value)
+                    .ok_or_else(|| anyhow!("bad boolean config value '{}' for '{}'", value, key)),
+                None => Ok(true),
+            })
+            .transpose()
+    }
+
+    pub fn integer(&self, key: &str) -> anyhow::Result<Option<i64>> {
+        self.raw(key)
+            .map(|value| {
+                let value = value.unwrap_or_default();
+                parse_integer(&value)
+                    .ok_or_else(|| anyhow!("bad numeric config value '{}' for '{}'", value, key))
+            })
+            .transpose()
+    }
+
+    /// Like [`Config::string`] but with a leading `~/` expanded to the home
+    /// directory.
+    pub fn path(&self, key: &str) -> anyhow::Result<Option<PathBuf>> {
+        self.string(key)
+            .map(|value| {
+                expand_path(&value, env::var_os("HOME").map(PathBuf::from))
+                    .ok_or_else(|| anyhow!("failed to expand '{}' for '{}'", value, key))
+            })
+            .transpose()
+    }
+}
+
+/// Which configuration file [`set`] and [`unset`] write to.
+#[derive(Clone, Copy)]
+pub enum Scope {
+    Global,
+    Local,
+}
+
+/// Writes a value with `git config`, since gitoxide can't write configuration
+/// files yet.
+pub fn set(scope: Scope, key: &str, value: &str) -> anyhow::Result<()> {
+    let output = git_config(scope).arg(key).arg(value).output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "failed to set {}: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// Removes a value with `git config --unset`, which is fine if it isn't set.
+pub fn unset(scope: Scope, key: &str) -> anyhow::Result<()> {
+    let output = git_config(scope).arg("--unset").arg(key).output()?;
+
+    // `git config --unset` exits with 5 when the key isn't set.
+    if output.status.success() || output.status.code() == Some(5) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "failed to unset {}: {}",
+            key,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+/// A `git config` command for `scope`, whose output should be captured so
+/// that it doesn't end up in the helper's stdout.
+fn git_config(scope: Scope) -> std::process::Command {
+    let scope = match scope {
+        Scope::Global => "--global",
+        Scope::Local => "--local",
+    };
+
+    let mut command = std::process::Command::new("git");
+    command.arg("config").arg(scope);
+    command
+}
+
+/// Section and variable names are case-insensitive, but subsections aren't.
+fn normalize_key(key: &str) -> String {
+    match (key.find('.'), key.rfind('.')) {
+        (Some(first), Some(last)) => format!(
+            "{}{}{}",
+            key[..first].to_lowercase(),
+            &key[first..last],
+            key[last..].to_lowercase()
+        ),
+        _ => key.to_lowercase(),
+    }
+}
+
+/// Reads the entries given with `git --config-env` or by setting
+/// `GIT_CONFIG_KEY_<n>` and `GIT_CONFIG_VALUE_<n>` directly.
+fn overrides_from_count() -> anyhow::Result<Vec<Entry>> {
+    let count = match env::var(GIT_CONFIG_COUNT) {
+        Ok(count) if !count.is_empty() => count,
+        _ => return Ok(Vec::new()),
+    };
+
+    let count = count
+        .parse::<usize>()
+        .map_err(|_| anyhow!("bogus count in {}", GIT_CONFIG_COUNT))?;
+
+    (0..count)
+        .map(|i| {
+            let key = env::var(format!("{}{}", GIT_CONFIG_KEY_PREFIX, i))
+                .map_err(|_| anyhow!("missing config key {}{}", GIT_CONFIG_KEY_PREFIX, i))?;
+            let value = env::var(format!("{}{}", GIT_CONFIG_VALUE_PREFIX, i))
+                .map_err(|_| anyhow!("missing config value {}{}", GIT_CONFIG_VALUE_PREFIX, i))?;
+            Ok((key, Some(value)))
+        })
+        .collect()
+}
+
+/// Parses `GIT_CONFIG_PARAMETERS`, which holds shell-quoted entries separated
+/// by spaces.
+///
+/// Older versions of git quote each entry as a whole (`'key=value'`) while
+/// newer versions quote the key and value separately (`'key'='value'`).
+fn parse_parameters(input: &str) -> anyhow::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            break Ok(entries);
+        }
+
+        let key = parse_quoted(&mut chars)?;
+
+        let entry = if chars.next_if_eq(&'=').is_some() {
+            let value = if chars.peek() == Some(&'\'') {
+                Some(parse_quoted(&mut chars)?)
+            } else {
+                // `git -c key=` with the new format
+                Some(String::new())
+            };
+            (key, value)
+        } else {
+            match key.split_once('=') {
+                Some((key, value)) => (key.to_owned(), Some(value.to_owned())),
+                None => (key, None),
+            }
+        };
+
+        if !chars.peek().map_or(true, |c| c.is_ascii_whitespace()) {
+            break Err(anyhow!("bogus format in {}", GIT_CONFIG_PARAMETERS));
+        }
+
+        entries.push(entry);
+    }
+}
+
+/// Parses a single-quoted string where embedded quotes are written as `'\''`.
+fn parse_quoted(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> anyhow::Result<String> {
+    let mut output = String::new();
+
+    if chars.next() != Some('\'') {
+        return Err(anyhow!("bogus format in {}", GIT_CONFIG_PARAMETERS));
+    }
+
+    loop {
+        match chars.next() {
+            Some('\'') => {
+                if chars.next_if_eq(&'\\').is_none() {
+                    break Ok(output);
+                }
+                match (chars.next(), chars.next()) {
+                    (Some(quote @ '\''), Some('\'')) => output.push(quote),
+                    _ => break Err(anyhow!("bogus format in {}", GIT_CONFIG_PARAMETERS)),
+                }
+            }
+            Some(c) => output.push(c),
+            None => break Err(anyhow!("bogus format in {}", GIT_CONFIG_PARAMETERS)),
+        }
+    }
+}
+
+pub fn parse_boolean(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" | "" => Some(false),
+        value => parse_integer(value).map(|value| value != 0),
+    }
+}
+
+/// Parses an integer with an optional `k`, `m` or `g` suffix.
+fn parse_integer(value: &str) -> Option<i64> {
+    let value = value.trim();
+
+    let (digits, factor) = match value.chars().last()?.to_ascii_lowercase() {
+        'k' => (&value[..value.len() - 1], 1024),
+        'm' => (&value[..value.len() - 1], 1024 * 1024),
+        'g' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+
+    digits.parse::<i64>().ok()?.checked_mul(factor)
+}
+
+fn expand_path(value: &str, home: Option<PathBuf>) -> Option<PathBuf> {
+    if value == "~" {
+        home
+    } else if let Some(rest) = value.strip_prefix("~/") {
+        home.map(|home| home.join(rest))
+    } else if value.starts_with('~') {
+        // `~user/` isn't supported.
+        None
     } else {
-        Ok(config_value)
+        Some(PathBuf::from(value))
     }
 }
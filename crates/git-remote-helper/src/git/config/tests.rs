@@ -0,0 +1,113 @@
+use super::*;
+
+#[test]
+fn test_parse_parameters() {
+    let input =
+        "'icp.fetchRootKey'='yes' 'icp.privateKey=~/key.pem' 'core.bare' 'user.name'='O'\\''Brien'";
+    let result = parse_parameters(input).expect("valid parameters");
+    assert_eq!(
+        result,
+        vec![
+            ("icp.fetchRootKey".into(), Some("yes".into())),
+            ("icp.privateKey".into(), Some("~/key.pem".into())),
+            ("core.bare".into(), None),
+            ("user.name".into(), Some("O'Brien".into())),
+        ],
+        "parameters"
+    );
+}
+
+#[test]
+fn test_parse_parameters_empty_value() {
+    let result = parse_parameters("'icp.replicaUrl'=").expect("valid parameters");
+    assert_eq!(
+        result,
+        vec![("icp.replicaUrl".into(), Some(String::new()))],
+        "parameters"
+    );
+}
+
+#[test]
+fn test_parse_parameters_bogus() {
+    assert!(
+        parse_parameters("icp.fetchRootKey=true").is_err(),
+        "unquoted"
+    );
+    assert!(
+        parse_parameters("'icp.fetchRootKey=true").is_err(),
+        "unterminated"
+    );
+}
+
+#[test]
+fn test_file_key_without_value() {
+    let file = "[icp]\n\tfetchRootKey\n\treplicaUrl =\n"
+        .parse::<git::config::File<'static>>()
+        .expect("valid config");
+    let config = Config {
+        file,
+        overrides: Vec::new(),
+    };
+
+    assert_eq!(config.raw("icp.fetchRootKey"), Some(None), "raw");
+    assert_eq!(
+        config.boolean("icp.fetchRootKey").ok(),
+        Some(Some(true)),
+        "no value"
+    );
+    assert_eq!(
+        config.boolean("icp.replicaUrl").ok(),
+        Some(Some(false)),
+        "empty value"
+    );
+    assert_eq!(
+        config.string("icp.replicaUrl"),
+        Some(String::new()),
+        "string"
+    );
+}
+
+#[test]
+fn test_normalize_key() {
+    assert_eq!(
+        normalize_key("Remote.Origin.IcpCanisterId"),
+        "remote.Origin.icpcanisterid",
+        "key"
+    );
+    assert_eq!(normalize_key("ICP.fetchRootKey"), "icp.fetchrootkey", "key");
+}
+
+#[test]
+fn test_parse_boolean() {
+    for value in ["true", "Yes", "on", "1", "2"] {
+        assert_eq!(parse_boolean(value), Some(true), "{}", value);
+    }
+    for value in ["false", "No", "off", "0", ""] {
+        assert_eq!(parse_boolean(value), Some(false), "{}", value);
+    }
+    assert_eq!(parse_boolean("maybe"), None, "maybe");
+}
+
+#[test]
+fn test_parse_integer() {
+    assert_eq!(parse_integer("42"), Some(42), "plain");
+    assert_eq!(parse_integer("2k"), Some(2048), "k");
+    assert_eq!(parse_integer("1M"), Some(1024 * 1024), "m");
+    assert_eq!(parse_integer("many"), None, "invalid");
+}
+
+#[test]
+fn test_expand_path() {
+    let home = Some(PathBuf::from("/home/user"));
+    assert_eq!(
+        expand_path("~/.config/key.pem", home.clone()),
+        Some(PathBuf::from("/home/user/.config/key.pem")),
+        "home"
+    );
+    assert_eq!(
+        expand_path("/etc/key.pem", home.clone()),
+        Some(PathBuf::from("/etc/key.pem")),
+        "absolute"
+    );
+    assert_eq!(expand_path("~other/key.pem", home), None, "other user");
+}
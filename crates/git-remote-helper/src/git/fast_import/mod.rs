@@ -0,0 +1,615 @@
+// https://git-scm.com/docs/git-fast-import#_input_format
+
+use git::bstr::{BStr, BString, ByteSlice as _};
+use git_repository as git;
+use std::io::{BufRead, Write};
+
+#[cfg(test)]
+mod tests;
+
+/// The subset of fast-import commands that `git fast-export` produces and that
+/// we need to produce for `git fast-import`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    Blob(Blob),
+    Commit(Commit),
+    Tag(Tag),
+    Reset(Reset),
+    Feature(BString),
+    Progress(BString),
+    Done,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Blob {
+    pub mark: Option<Mark>,
+    pub data: BString,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Commit {
+    pub ref_name: BString,
+    pub mark: Option<Mark>,
+    pub author: Option<git::actor::Signature>,
+    pub committer: git::actor::Signature,
+    pub signatures: Vec<CommitSignature>,
+    pub encoding: Option<BString>,
+    pub message: BString,
+    pub from: Option<DataRef>,
+    pub merge: Vec<DataRef>,
+    pub file_changes: Vec<FileChange>,
+}
+
+/// A `gpgsig` signature of a commit, which git passes through verbatim so
+/// that the commit keeps its id.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommitSignature {
+    /// The hash algorithm and signature format, as in `sha1 openpgp`.
+    pub header: BString,
+    pub data: BString,
+}
+
+/// An annotated tag.
+///
+/// When the `signed-tags` capability is advertised git passes signatures
+/// through verbatim as part of the message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tag {
+    pub name: BString,
+    pub mark: Option<Mark>,
+    pub from: DataRef,
+    pub tagger: Option<git::actor::Signature>,
+    pub message: BString,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reset {
+    pub ref_name: BString,
+    pub from: Option<DataRef>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Mark(pub u64);
+
+/// A reference to an object, either by a mark set earlier in the stream or by
+/// its id.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DataRef {
+    Mark(Mark),
+    Id(git::hash::ObjectId),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileChange {
+    Modify {
+        mode: u32,
+        data_ref: DataRef,
+        path: BString,
+    },
+    Delete {
+        path: BString,
+    },
+    DeleteAll,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    Io(String),
+    InvalidDataRef(BString),
+    InvalidMark(BString),
+    InvalidMode(BString),
+    InvalidPath(BString),
+    InvalidSignature(BString),
+    UnexpectedEnd,
+    UnexpectedLine(BString),
+    UnsupportedCommand(BString),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            Self::Io(err) => format!("IO error: {}", err),
+            Self::InvalidDataRef(line) => format!("invalid dataref: {}", line),
+            Self::InvalidMark(line) => format!("invalid mark: {}", line),
+            Self::InvalidMode(line) => format!("invalid mode: {}", line),
+            Self::InvalidPath(line) => format!("invalid path: {}", line),
+            Self::InvalidSignature(line) => format!("invalid signature: {}", line),
+            Self::UnexpectedEnd => "unexpected end of stream".to_string(),
+            Self::UnexpectedLine(line) => format!("unexpected line: {}", line),
+            Self::UnsupportedCommand(line) => format!("unsupported command: {}", line),
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<std::io::Error> for ParseError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err.to_string())
+    }
+}
+
+/// Reads commands from a fast-export stream up to and including `done`, or
+/// until the stream ends.
+pub fn read(input: &mut impl BufRead) -> Result<Vec<Command>, ParseError> {
+    let mut reader = Reader {
+        input,
+        peeked: None,
+    };
+    let mut commands = Vec::new();
+
+    while let Some(line) = reader.next_line()? {
+        let command = if line.is_empty() {
+            // Commands may be separated by an optional blank line
+            continue;
+        } else if line == "blob" {
+            Command::Blob(reader.read_blob()?)
+        } else if let Some(ref_name) = line.strip_prefix(b"commit ") {
+            Command::Commit(reader.read_commit(ref_name.into())?)
+        } else if let Some(name) = line.strip_prefix(b"tag ") {
+            Command::Tag(reader.read_tag(name.into())?)
+        } else if let Some(ref_name) = line.strip_prefix(b"reset ") {
+            Command::Reset(reader.read_reset(ref_name.into())?)
+        } else if let Some(feature) = line.strip_prefix(b"feature ") {
+            Command::Feature(feature.into())
+        } else if let Some(message) = line.strip_prefix(b"progress ") {
+            Command::Progress(message.into())
+        } else if line == "done" {
+            commands.push(Command::Done);
+            break;
+        } else {
+            return Err(ParseError::UnsupportedCommand(line));
+        };
+
+        commands.push(command);
+    }
+
+    Ok(commands)
+}
+
+struct Reader<'a, R> {
+    input: &'a mut R,
+    peeked: Option<BString>,
+}
+
+impl<'a, R: BufRead> Reader<'a, R> {
+    /// The next line without its trailing newline, or `None` at the end of
+    /// the stream.
+    fn next_line(&mut self) -> Result<Option<BString>, ParseError> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+
+        let mut line = Vec::new();
+        if self.input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        Ok(Some(line.into()))
+    }
+
+    fn expect_line(&mut self) -> Result<BString, ParseError> {
+        self.next_line()?.ok_or(ParseError::UnexpectedEnd)
+    }
+
+    /// Reads the next line if it starts with `prefix`, returning the rest.
+    fn next_line_with_prefix(&mut self, prefix: &str) -> Result<Option<BString>, ParseError> {
+        let line = match self.next_line()? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        if let Some(rest) = line.strip_prefix(prefix.as_bytes()) {
+            return Ok(Some(rest.into()));
+        }
+
+        self.peeked = Some(line);
+        Ok(None)
+    }
+
+    fn read_mark(&mut self) -> Result<Option<Mark>, ParseError> {
+        self.next_line_with_prefix("mark ")?
+            .map(|mark| parse_mark(mark.as_bstr()))
+            .transpose()
+    }
+
+    /// `original-oid` is only informational so it is skipped.
+    fn skip_original_oid(&mut self) -> Result<(), ParseError> {
+        self.next_line_with_prefix("original-oid ")?;
+        Ok(())
+    }
+
+    fn read_signature(
+        &mut self,
+        prefix: &str,
+    ) -> Result<Option<git::actor::Signature>, ParseError> {
+        self.next_line_with_prefix(prefix)?
+            .map(|signature| parse_signature(signature.as_bstr()))
+            .transpose()
+    }
+
+    /// Reads a `data` command in its exact byte count format, which is the
+    /// only format that `git fast-export` produces.
+    fn read_data(&mut self) -> Result<BString, ParseError> {
+        let line = self.expect_line()?;
+        let count = line
+            .strip_prefix(b"data ")
+            .and_then(|count| count.to_str().ok())
+            .and_then(|count| count.parse::<usize>().ok())
+            .ok_or_else(|| ParseError::UnexpectedLine(line.clone()))?;
+
+        let mut data = vec![0; count];
+        self.input.read_exact(&mut data)?;
+
+        // The data may be followed by an optional newline
+        if self.input.fill_buf()?.first() == Some(&b'\n') {
+            self.input.consume(1);
+        }
+
+        Ok(data.into())
+    }
+
+    fn read_blob(&mut self) -> Result<Blob, ParseError> {
+        let mark = self.read_mark()?;
+        self.skip_original_oid()?;
+        let data = self.read_data()?;
+        Ok(Blob { mark, data })
+    }
+
+    fn read_commit(&mut self, ref_name: BString) -> Result<Commit, ParseError> {
+        let mark = self.read_mark()?;
+        self.skip_original_oid()?;
+        let author = self.read_signature("author ")?;
+        let committer = self
+            .read_signature("committer ")?
+            .ok_or(ParseError::UnexpectedEnd)?;
+
+        let mut signatures = Vec::new();
+        while let Some(header) = self.next_line_with_prefix("gpgsig ")? {
+            let data = self.read_data()?;
+            signatures.push(CommitSignature { header, data });
+        }
+
+        let encoding = self.next_line_with_prefix("encoding ")?;
+        let message = self.read_data()?;
+        let from = self
+            .next_line_with_prefix("from ")?
+            .map(|from| parse_data_ref(from.as_bstr()))
+            .transpose()?;
+
+        let mut merge = Vec::new();
+        while let Some(parent) = self.next_line_with_prefix("merge ")? {
+            merge.push(parse_data_ref(parent.as_bstr())?);
+        }
+
+        let mut file_changes = Vec::new();
+        while let Some(line) = self.next_line()? {
+            if line.is_empty() {
+                break;
+            } else if let Some(file_change) = parse_file_change(line.as_bstr())? {
+                file_changes.push(file_change);
+            } else {
+                self.peeked = Some(line);
+                break;
+            }
+        }
+
+        Ok(Commit {
+            ref_name,
+            mark,
+            author,
+            committer,
+            signatures,
+            encoding,
+            message,
+            from,
+            merge,
+            file_changes,
+        })
+    }
+
+    fn read_tag(&mut self, name: BString) -> Result<Tag, ParseError> {
+        let mark = self.read_mark()?;
+        let from = self
+            .next_line_with_prefix("from ")?
+            .ok_or(ParseError::UnexpectedEnd)
+            .and_then(|from| parse_data_ref(from.as_bstr()))?;
+        self.skip_original_oid()?;
+        let tagger = self.read_signature("tagger ")?;
+        let message = self.read_data()?;
+
+        Ok(Tag {
+            name,
+            mark,
+            from,
+            tagger,
+            message,
+        })
+    }
+
+    fn read_reset(&mut self, ref_name: BString) -> Result<Reset, ParseError> {
+        let from = self
+            .next_line_with_prefix("from ")?
+            .map(|from| parse_data_ref(from.as_bstr()))
+            .transpose()?;
+
+        Ok(Reset { ref_name, from })
+    }
+}
+
+fn parse_mark(input: &BStr) -> Result<Mark, ParseError> {
+    input
+        .strip_prefix(b":")
+        .and_then(|mark| mark.to_str().ok())
+        .and_then(|mark| mark.parse::<u64>().ok())
+        .map(Mark)
+        .ok_or_else(|| ParseError::InvalidMark(input.to_owned()))
+}
+
+fn parse_data_ref(input: &BStr) -> Result<DataRef, ParseError> {
+    if input.starts_with(b":") {
+        parse_mark(input).map(DataRef::Mark)
+    } else {
+        git::hash::ObjectId::from_hex(input)
+            .map(DataRef::Id)
+            .map_err(|_| ParseError::InvalidDataRef(input.to_owned()))
+    }
+}
+
+fn parse_signature(input: &BStr) -> Result<git::actor::Signature, ParseError> {
+    git::actor::SignatureRef::from_bytes::<()>(input)
+        .map(|signature| signature.to_owned())
+        .map_err(|_| ParseError::InvalidSignature(input.to_owned()))
+}
+
+/// Parses a file change, or returns `None` if the line isn't one.
+fn parse_file_change(input: &BStr) -> Result<Option<FileChange>, ParseError> {
+    if input == "deleteall" {
+        Ok(Some(FileChange::DeleteAll))
+    } else if let Some(rest) = input.strip_prefix(b"M ") {
+        let mut parts = rest.splitn(3, |b| *b == b' ');
+        let (mode, data_ref, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(mode), Some(data_ref), Some(path)) => (mode, data_ref, path),
+            _ => return Err(ParseError::UnexpectedLine(input.to_owned())),
+        };
+        let mode = mode
+            .to_str()
+            .ok()
+            .and_then(|mode| u32::from_str_radix(mode, 8).ok())
+            .ok_or_else(|| ParseError::InvalidMode(mode.into()))?;
+        let data_ref = parse_data_ref(data_ref.as_bstr())?;
+        let path = parse_path(path.as_bstr())?;
+        Ok(Some(FileChange::Modify {
+            mode,
+            data_ref,
+            path,
+        }))
+    } else if let Some(path) = input.strip_prefix(b"D ") {
+        let path = parse_path(path.as_bstr())?;
+        Ok(Some(FileChange::Delete { path }))
+    } else if input.starts_with(b"C ") || input.starts_with(b"R ") || input.starts_with(b"N ") {
+        // Only produced by `git fast-export` with options we don't pass.
+        Err(ParseError::UnsupportedCommand(input.to_owned()))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Parses a path, which git quotes C-style if it contains special characters.
+fn parse_path(input: &BStr) -> Result<BString, ParseError> {
+    let quoted = match input
+        .strip_prefix(b"\"")
+        .and_then(|rest| rest.strip_suffix(b"\""))
+    {
+        Some(quoted) => quoted,
+        None => return Ok(input.to_owned()),
+    };
+
+    let invalid = || ParseError::InvalidPath(input.to_owned());
+    let mut path = Vec::with_capacity(quoted.len());
+    let mut bytes = quoted.iter().copied();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            path.push(byte);
+            continue;
+        }
+
+        let escaped = bytes.next().ok_or_else(invalid)?;
+        let unescaped = match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'v' => 0x0b,
+            b'"' | b'\\' => escaped,
+            b'0'..=b'7' => {
+                let digits = [
+                    escaped,
+                    bytes.next().ok_or_else(invalid)?,
+                    bytes.next().ok_or_else(invalid)?,
+                ];
+                std::str::from_utf8(&digits)
+                    .ok()
+                    .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+                    .ok_or_else(invalid)?
+            }
+            _ => return Err(invalid()),
+        };
+        path.push(unescaped);
+    }
+
+    Ok(path.into())
+}
+
+/// Quotes a path C-style if it contains characters that would otherwise be
+/// ambiguous.
+fn quote_path(path: &BStr) -> BString {
+    let needs_quoting = path.starts_with(b"\"")
+        || path
+            .iter()
+            .any(|byte| matches!(byte, b'\n' | b'\\' | b'"') || byte.is_ascii_control());
+
+    if !needs_quoting {
+        return path.to_owned();
+    }
+
+    let mut quoted = vec![b'"'];
+    for byte in path.iter() {
+        match byte {
+            b'\n' => quoted.extend_from_slice(b"\\n"),
+            b'\t' => quoted.extend_from_slice(b"\\t"),
+            b'"' => quoted.extend_from_slice(b"\\\""),
+            b'\\' => quoted.extend_from_slice(b"\\\\"),
+            byte if byte.is_ascii_control() => {
+                quoted.extend_from_slice(format!("\\{:03o}", byte).as_bytes())
+            }
+            byte => quoted.push(*byte),
+        }
+    }
+    quoted.push(b'"');
+    quoted.into()
+}
+
+impl std::fmt::Display for Mark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, ":{}", self.0)
+    }
+}
+
+impl std::fmt::Display for DataRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mark(mark) => write!(f, "{}", mark),
+            Self::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+/// Writes commands as a fast-import stream.
+pub fn write(out: &mut impl Write, commands: &[Command]) -> std::io::Result<()> {
+    commands
+        .iter()
+        .try_for_each(|command| command.write_to(&mut *out))
+}
+
+impl Command {
+    pub fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        match self {
+            Self::Blob(blob) => {
+                writeln!(out, "blob")?;
+                write_mark(out, blob.mark)?;
+                write_data(out, blob.data.as_bstr())
+            }
+            Self::Commit(commit) => {
+                out.write_all(b"commit ")?;
+                out.write_all(&commit.ref_name)?;
+                writeln!(out)?;
+                write_mark(out, commit.mark)?;
+                if let Some(author) = &commit.author {
+                    write_signature(out, "author", author)?;
+                }
+                write_signature(out, "committer", &commit.committer)?;
+                for signature in &commit.signatures {
+                    out.write_all(b"gpgsig ")?;
+                    out.write_all(&signature.header)?;
+                    writeln!(out)?;
+                    write_data(out, signature.data.as_bstr())?;
+                }
+                if let Some(encoding) = &commit.encoding {
+                    out.write_all(b"encoding ")?;
+                    out.write_all(encoding)?;
+                    writeln!(out)?;
+                }
+                write_data(out, commit.message.as_bstr())?;
+                if let Some(from) = &commit.from {
+                    writeln!(out, "from {}", from)?;
+                }
+                for parent in &commit.merge {
+                    writeln!(out, "merge {}", parent)?;
+                }
+                for file_change in &commit.file_changes {
+                    match file_change {
+                        FileChange::Modify {
+                            mode,
+                            data_ref,
+                            path,
+                        } => {
+                            write!(out, "M {:06o} {} ", mode, data_ref)?;
+                            out.write_all(&quote_path(path.as_bstr()))?;
+                            writeln!(out)?;
+                        }
+                        FileChange::Delete { path } => {
+                            out.write_all(b"D ")?;
+                            out.write_all(&quote_path(path.as_bstr()))?;
+                            writeln!(out)?;
+                        }
+                        FileChange::DeleteAll => writeln!(out, "deleteall")?,
+                    }
+                }
+                writeln!(out)
+            }
+            Self::Tag(tag) => {
+                out.write_all(b"tag ")?;
+                out.write_all(&tag.name)?;
+                writeln!(out)?;
+                write_mark(out, tag.mark)?;
+                writeln!(out, "from {}", tag.from)?;
+                if let Some(tagger) = &tag.tagger {
+                    write_signature(out, "tagger", tagger)?;
+                }
+                write_data(out, tag.message.as_bstr())
+            }
+            Self::Reset(reset) => {
+                out.write_all(b"reset ")?;
+                out.write_all(&reset.ref_name)?;
+                writeln!(out)?;
+                if let Some(from) = &reset.from {
+                    writeln!(out, "from {}", from)?;
+                }
+                writeln!(out)
+            }
+            Self::Feature(feature) => {
+                out.write_all(b"feature ")?;
+                out.write_all(feature)?;
+                writeln!(out)
+            }
+            Self::Progress(message) => {
+                out.write_all(b"progress ")?;
+                out.write_all(message)?;
+                writeln!(out)
+            }
+            Self::Done => writeln!(out, "done"),
+        }
+    }
+}
+
+fn write_mark(out: &mut impl Write, mark: Option<Mark>) -> std::io::Result<()> {
+    match mark {
+        Some(mark) => writeln!(out, "mark {}", mark),
+        None => Ok(()),
+    }
+}
+
+fn write_signature(
+    out: &mut impl Write,
+    kind: &str,
+    signature: &git::actor::Signature,
+) -> std::io::Result<()> {
+    write!(out, "{} ", kind)?;
+    signature.write_to(&mut *out)?;
+    writeln!(out)
+}
+
+fn write_data(out: &mut impl Write, data: &BStr) -> std::io::Result<()> {
+    writeln!(out, "data {}", data.len())?;
+    out.write_all(data)?;
+    writeln!(out)
+}
@@ -0,0 +1,191 @@
+use super::*;
+
+const COMMIT_ID: &[u8] = b"91536083cdb16ef3c29638054642b50a34ea8c25";
+
+fn signature(input: &str) -> git::actor::Signature {
+    parse_signature(input.into()).expect("valid signature")
+}
+
+fn commit() -> Commit {
+    Commit {
+        ref_name: "refs/heads/main".into(),
+        mark: Some(Mark(2)),
+        author: Some(signature("Author <author@example.com> 1672531200 +0000")),
+        committer: signature("Committer <committer@example.com> 1672531200 +0100"),
+        signatures: Vec::new(),
+        encoding: None,
+        message: "Initial commit\n".into(),
+        from: Some(DataRef::Id(
+            git::hash::ObjectId::from_hex(COMMIT_ID).expect("valid hex object id"),
+        )),
+        merge: Vec::new(),
+        file_changes: vec![
+            FileChange::Modify {
+                mode: 0o100644,
+                data_ref: DataRef::Mark(Mark(1)),
+                path: "README.md".into(),
+            },
+            FileChange::Delete {
+                path: "with \"quotes\"".into(),
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_read_fast_export_stream() {
+    let input = [
+        "feature done",
+        "blob",
+        "mark :1",
+        "data 6",
+        "hello",
+        "",
+        "reset refs/heads/main",
+        "commit refs/heads/main",
+        "mark :2",
+        "author Author <author@example.com> 1672531200 +0000",
+        "committer Committer <committer@example.com> 1672531200 +0100",
+        "data 15",
+        "Initial commit",
+        "from 91536083cdb16ef3c29638054642b50a34ea8c25",
+        "M 100644 :1 README.md",
+        "D \"with \\\"quotes\\\"\"",
+        "",
+        "tag v1.0.0",
+        "from :2",
+        "tagger Tagger <tagger@example.com> 1672531200 +0000",
+        "data 7",
+        "v1.0.0",
+        "done",
+        "",
+    ]
+    .join("\n");
+
+    let result = read(&mut input.as_bytes());
+
+    assert_eq!(
+        result,
+        Ok(vec![
+            Command::Feature("done".into()),
+            Command::Blob(Blob {
+                mark: Some(Mark(1)),
+                data: "hello\n".into(),
+            }),
+            Command::Reset(Reset {
+                ref_name: "refs/heads/main".into(),
+                from: None,
+            }),
+            Command::Commit(commit()),
+            Command::Tag(Tag {
+                name: "v1.0.0".into(),
+                mark: None,
+                from: DataRef::Mark(Mark(2)),
+                tagger: Some(signature("Tagger <tagger@example.com> 1672531200 +0000")),
+                message: "v1.0.0\n".into(),
+            }),
+            Command::Done,
+        ]),
+        "fast-export stream"
+    )
+}
+
+#[test]
+fn test_read_signed_commit() {
+    let gpgsig = "-----BEGIN PGP SIGNATURE-----\n\niQEz\n-----END PGP SIGNATURE-----\n";
+    let gpgsig_data = format!("data {}", gpgsig.len());
+    // The signature ends with a newline so the next command follows directly.
+    let gpgsig_encoding = format!("{}encoding ISO-8859-1", gpgsig);
+    let input = [
+        "commit refs/heads/main",
+        "mark :2",
+        "author Author <author@example.com> 1672531200 +0000",
+        "committer Committer <committer@example.com> 1672531200 +0100",
+        "gpgsig sha1 openpgp",
+        gpgsig_data.as_str(),
+        gpgsig_encoding.as_str(),
+        "data 15",
+        "Initial commit",
+        "from 91536083cdb16ef3c29638054642b50a34ea8c25",
+        "M 100644 :1 README.md",
+        "D \"with \\\"quotes\\\"\"",
+        "",
+    ]
+    .join("\n");
+
+    let signed = Commit {
+        signatures: vec![CommitSignature {
+            header: "sha1 openpgp".into(),
+            data: gpgsig.into(),
+        }],
+        encoding: Some("ISO-8859-1".into()),
+        ..commit()
+    };
+
+    let result = read(&mut input.as_bytes());
+    assert_eq!(
+        result,
+        Ok(vec![Command::Commit(signed.clone())]),
+        "fast-export stream"
+    );
+
+    let mut output = Vec::new();
+    write(&mut output, &[Command::Commit(signed.clone())]).expect("write fast-import stream");
+    let result = read(&mut output.as_slice());
+    assert_eq!(result, Ok(vec![Command::Commit(signed)]), "round trip")
+}
+
+#[test]
+fn test_read_unsupported_command() {
+    let input = "checkpoint\n";
+    let result = read(&mut input.as_bytes());
+    assert_eq!(
+        result,
+        Err(ParseError::UnsupportedCommand("checkpoint".into())),
+        "fast-export stream"
+    )
+}
+
+#[test]
+fn test_read_truncated_data() {
+    let input = "blob\ndata 10\nshort";
+    let result = read(&mut input.as_bytes());
+    assert!(
+        matches!(result, Err(ParseError::Io(_))),
+        "fast-export stream"
+    )
+}
+
+#[test]
+fn test_write_read_round_trip() {
+    let commands = vec![
+        Command::Blob(Blob {
+            mark: Some(Mark(1)),
+            data: "hello\n".into(),
+        }),
+        Command::Commit(commit()),
+        Command::Done,
+    ];
+
+    let mut output = Vec::new();
+    write(&mut output, &commands).expect("write fast-import stream");
+
+    let result = read(&mut output.as_slice());
+    assert_eq!(result, Ok(commands), "fast-import stream")
+}
+
+#[test]
+fn test_parse_path_octal_escape() {
+    let result = parse_path("\"caf\\303\\251\"".into());
+    assert_eq!(result, Ok(BString::from("café")), "path")
+}
+
+#[test]
+fn test_quote_path() {
+    assert_eq!(quote_path("plain".into()), BString::from("plain"), "path");
+    assert_eq!(
+        quote_path("a\nb".into()),
+        BString::from("\"a\\nb\""),
+        "path"
+    );
+}
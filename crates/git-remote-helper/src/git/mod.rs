@@ -1,2 +1,5 @@
 pub mod config;
+pub mod fast_import;
+pub mod object_format;
 pub mod service;
+pub mod transport;
@@ -0,0 +1,67 @@
+// https://git-scm.com/docs/protocol-capabilities#_object_format
+//
+// Only SHA-1 is supported. SHA-256 repositories and servers are explicitly
+// unsupported until gitoxide supports them, and are rejected with an error
+// rather than misread.
+
+use anyhow::anyhow;
+use git::bstr::{BStr, ByteSlice as _};
+use git::protocol::transport::client::Capabilities;
+use git_repository as git;
+
+#[cfg(test)]
+mod tests;
+
+const OBJECT_FORMAT: &str = "object-format";
+
+/// The name of an object format as used by the `object-format` capability.
+pub fn name(kind: git::hash::Kind) -> &'static str {
+    match kind {
+        git::hash::Kind::Sha1 => "sha1",
+    }
+}
+
+/// Parses the value of an `object-format` capability.
+pub fn from_name(name: &BStr) -> anyhow::Result<git::hash::Kind> {
+    match name.as_bytes() {
+        b"sha1" => Ok(git::hash::Kind::Sha1),
+        b"sha256" => Err(anyhow!(
+            "SHA-256 repositories are unsupported until gitoxide supports them, only sha1 can be used"
+        )),
+        _ => Err(anyhow!("unknown object format: {}", name)),
+    }
+}
+
+/// The object format advertised by the server, which is SHA-1 unless stated
+/// otherwise.
+pub fn advertised(capabilities: &Capabilities) -> anyhow::Result<git::hash::Kind> {
+    match capabilities
+        .capability(OBJECT_FORMAT)
+        .and_then(|object_format| object_format.value().map(ToOwned::to_owned))
+    {
+        Some(name) => from_name(name.as_bstr()),
+        None => Ok(git::hash::Kind::Sha1),
+    }
+}
+
+/// Whether the server advertised the `object-format` capability, in which case
+/// we should state the object format we use in requests.
+pub fn is_advertised(capabilities: &Capabilities) -> bool {
+    capabilities.contains(OBJECT_FORMAT)
+}
+
+/// Ensures that the local repository and the server use the same object
+/// format.
+pub fn ensure_matches(local: git::hash::Kind, capabilities: &Capabilities) -> anyhow::Result<()> {
+    let remote = advertised(capabilities)?;
+
+    if local == remote {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "the local repository uses the {} object format but the remote uses {}",
+            name(local),
+            name(remote)
+        ))
+    }
+}
@@ -0,0 +1,29 @@
+use super::*;
+
+#[test]
+fn test_from_name_sha1() {
+    let result = from_name("sha1".into());
+    assert_eq!(result.ok(), Some(git::hash::Kind::Sha1), "object-format")
+}
+
+#[test]
+fn test_from_name_sha256() {
+    let result = from_name("sha256".into());
+    assert!(result.is_err(), "object-format")
+}
+
+#[test]
+fn test_from_name_unknown() {
+    let result = from_name("md5".into());
+    assert!(result.is_err(), "object-format")
+}
+
+#[test]
+fn test_name_round_trip() {
+    let kind = git::hash::Kind::Sha1;
+    assert_eq!(
+        from_name(name(kind).into()).ok(),
+        Some(kind),
+        "object-format"
+    )
+}
@@ -0,0 +1,107 @@
+use git::protocol::transport::client::{
+    Capabilities, Error, MessageKind, RequestWriter, SetServiceResponse, Transport,
+    TransportWithoutIO, WriteMode,
+};
+use git::protocol::transport::{Protocol, Service};
+use git_repository as git;
+use log::trace;
+use maybe_async::maybe_async;
+use std::borrow::Cow;
+
+/// A transport that `list` has already handshaken with upload-pack, which
+/// answers the next upload-pack handshake with the capabilities it saw rather
+/// than asking the server again.
+///
+/// Only the protocol v2 handshake can be answered this way since it doesn't
+/// include the refs, which have been read by then. With older protocols the
+/// handshake is passed on to the server.
+pub struct Handshaken<T> {
+    transport: T,
+    capabilities: Option<Capabilities>,
+}
+
+impl<T> Handshaken<T>
+where
+    T: Transport,
+{
+    pub fn new(transport: T, outcome: git::protocol::handshake::Outcome) -> Self {
+        let capabilities = match outcome.server_protocol_version {
+            Protocol::V2 => Some(outcome.capabilities),
+            _ => None,
+        };
+
+        Self {
+            transport,
+            capabilities,
+        }
+    }
+
+    /// Whether the handshake can be answered or the transport can perform
+    /// another one. A persistent connection that spoke an older protocol has
+    /// already sent its refs and can't.
+    pub fn is_reusable(&self) -> bool {
+        self.capabilities.is_some()
+            || !self
+                .transport
+                .connection_persists_across_multiple_requests()
+    }
+}
+
+impl<T> TransportWithoutIO for Handshaken<T>
+where
+    T: Transport,
+{
+    fn request(
+        &mut self,
+        write_mode: WriteMode,
+        on_into_read: MessageKind,
+    ) -> Result<RequestWriter<'_>, Error> {
+        self.transport.request(write_mode, on_into_read)
+    }
+
+    fn to_url(&self) -> Cow<'_, git::bstr::BStr> {
+        self.transport.to_url()
+    }
+
+    fn supported_protocol_versions(&self) -> &[Protocol] {
+        self.transport.supported_protocol_versions()
+    }
+
+    fn connection_persists_across_multiple_requests(&self) -> bool {
+        self.transport
+            .connection_persists_across_multiple_requests()
+    }
+
+    fn configure(
+        &mut self,
+        config: &dyn std::any::Any,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.transport.configure(config)
+    }
+}
+
+#[maybe_async(?Send)]
+impl<T> Transport for Handshaken<T>
+where
+    T: Transport,
+{
+    async fn handshake<'a>(
+        &mut self,
+        service: Service,
+        extra_parameters: &'a [(&'a str, Option<&'a str>)],
+    ) -> Result<SetServiceResponse<'_>, Error> {
+        if matches!(service, Service::UploadPack) {
+            if let Some(capabilities) = self.capabilities.take() {
+                trace!("reusing upload-pack handshake");
+
+                return Ok(SetServiceResponse {
+                    actual_protocol: Protocol::V2,
+                    capabilities,
+                    refs: None,
+                });
+            }
+        }
+
+        self.transport.handshake(service, extra_parameters).await
+    }
+}
@@ -0,0 +1,33 @@
+use git_repository as gitoxide;
+use std::sync::atomic::Ordering;
+
+/// The exit status when interrupted, following the shell convention of
+/// 128 + SIGINT.
+pub const EXIT_STATUS: i32 = 130;
+
+/// Installs the interrupt handler.
+///
+/// The handler runs in a signal handler so it only deletes gitoxide's
+/// temporary files, like partially written packs, and sets
+/// `gitoxide::interrupt::IS_INTERRUPTED`. Long running operations check the
+/// flag and stop in an orderly fashion.
+pub fn init_handler() -> anyhow::Result<()> {
+    gitoxide::interrupt::init_handler(gitoxide::tempfile::handler::cleanup_tempfiles)?;
+    Ok(())
+}
+
+pub fn is_interrupted() -> bool {
+    gitoxide::interrupt::IS_INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// The error returned when an operation stopped because of an interrupt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
@@ -3,10 +3,12 @@
 pub mod cli;
 pub mod commands;
 pub mod git;
+pub mod interrupt;
 
 use anyhow::{anyhow, Context};
-use clap::{Command, FromArgMatches as _, Parser as _, Subcommand as _};
+use clap::Parser as _;
 use cli::Args;
+use commands::list::ListVariant;
 use commands::Commands;
 use git_repository as gitoxide;
 use gitoxide::protocol::transport;
@@ -14,14 +16,19 @@ use log::trace;
 use maybe_async::maybe_async;
 use std::collections::BTreeSet;
 use std::env;
-use std::path::Path;
-use strum::VariantNames as _;
+
+pub use commands::capabilities::Capabilities;
+pub use commands::connect::Connection;
+pub use commands::import::ImportExport;
 
 #[cfg(all(feature = "async-network-client", feature = "blocking-network-client"))]
 compile_error!("Cannot set both 'async-network-client' and 'blocking-network-client' features as they are mutually exclusive");
 
 const GIT_DIR: &str = "GIT_DIR";
 
+/// The exit status used by git itself for `die()`.
+const FATAL_EXIT_STATUS: i32 = 128;
+
 #[maybe_async]
 pub async fn main<C>(
     connect: impl Fn(String, transport::client::connect::Options) -> C,
@@ -34,27 +41,74 @@ where
         >,
     >,
 {
-    let args = Args::parse();
-    trace!("args.repository: {:?}", args.repository);
-    trace!("args.url: {:?}", args.url);
+    main_with_capabilities(connect, Capabilities::default()).await
+}
 
-    gitoxide::interrupt::init_handler(move || {})?;
+#[maybe_async]
+pub async fn main_with_capabilities<C>(
+    connect: impl Fn(String, transport::client::connect::Options) -> C,
+    capabilities: Capabilities,
+) -> anyhow::Result<()>
+where
+    C: std::future::Future<
+        Output = Result<
+            Box<(dyn transport::client::Transport + Send)>,
+            transport::client::connect::Error,
+        >,
+    >,
+{
+    interrupt::init_handler()?;
 
-    let git_dir = env::var(GIT_DIR).context("failed to get GIT_DIR")?;
-    trace!("GIT_DIR: {}", git_dir);
+    let result = run(connect, capabilities).await;
 
-    let repo_dir = Path::new(&git_dir)
-        .parent()
-        .ok_or_else(|| anyhow!("failed to get repository directory"))?;
+    // Anything that was in progress has already been cleaned up by now, so
+    // all that's left is to tell git why we stopped.
+    if interrupt::is_interrupted() {
+        eprintln!("fatal: interrupted");
+        std::process::exit(interrupt::EXIT_STATUS);
+    }
 
-    let repo = gitoxide::open(repo_dir)?;
+    // Git only relays our stderr, so report commands we can't handle the way
+    // it reports its own fatal errors rather than with a usage message.
+    if let Err(err) = &result {
+        if let Some(err) = err.downcast_ref::<commands::ParseError>() {
+            eprintln!("fatal: {}", err);
+            std::process::exit(FATAL_EXIT_STATUS);
+        }
+    }
+
+    result
+}
+
+#[maybe_async]
+async fn run<C>(
+    connect: impl Fn(String, transport::client::connect::Options) -> C,
+    mut capabilities: Capabilities,
+) -> anyhow::Result<()>
+where
+    C: std::future::Future<
+        Output = Result<
+            Box<(dyn transport::client::Transport + Send)>,
+            transport::client::connect::Error,
+        >,
+    >,
+{
+    let args = Args::parse();
+    trace!("args.repository: {:?}", args.repository);
+    trace!("args.url: {:?}", args.url);
+
+    let repo = open_repository()?;
 
     // TODO: implementer provides this
     let authenticate =
         |action| panic!("unexpected call to authenticate with action: {:#?}", action);
 
     let mut fetch: commands::fetch::Batch = BTreeSet::new();
+    let mut import: commands::import::Batch = BTreeSet::new();
     let mut push: commands::push::Batch = BTreeSet::new();
+    let mut push_advertisement = None;
+    let mut upload_pack_transport = None;
+    let mut options = commands::option::Options::default();
 
     loop {
         trace!("loop");
@@ -62,86 +116,262 @@ where
         // TODO: BString?
         let mut input = String::new();
 
-        std::io::stdin()
+        let read = std::io::stdin()
             .read_line(&mut input)
             .context("failed to read from stdin")?;
 
+        // Git closes stdin once it has nothing more to ask, e.g. after
+        // `capabilities` and `list` for `git ls-remote`.
+        if read == 0 {
+            trace!("terminated with EOF");
+            break Ok(());
+        }
+
         let input = input.trim();
 
         if input.is_empty() {
             trace!("terminated with a blank line");
 
-            let fetch_transport = connect(
-                args.url.clone(),
-                transport::client::connect::Options {
-                    version: transport::Protocol::V2,
-                    #[cfg(feature = "blocking-network-client")]
-                    ssh: Default::default(),
-                },
-            )
-            .await?;
-
-            commands::fetch::process(fetch_transport, &repo, &args.url, &mut fetch).await?;
-
-            // NOTE: push still uses the v1 protocol so we use that here.
-            let mut push_transport = connect(
-                args.url.clone(),
-                transport::client::connect::Options {
-                    version: transport::Protocol::V1,
-                    #[cfg(feature = "blocking-network-client")]
-                    ssh: Default::default(),
-                },
-            )
-            .await?;
-
-            commands::push::process(&mut push_transport, &repo, authenticate, &mut push).await?;
+            if !import.is_empty() {
+                let backend = capabilities
+                    .import_export
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("import is not supported"))?;
+
+                commands::import::process(backend.as_mut(), &capabilities.refspecs, &mut import)?;
+            }
+
+            if !fetch.is_empty() {
+                let repo = require_repository(&repo, "fetch")?;
+
+                let fetch_transport: Box<dyn transport::client::Transport + Send> =
+                    match upload_pack_transport.take() {
+                        Some(transport) => Box::new(transport),
+                        None => {
+                            connect(
+                                args.url.clone(),
+                                transport::client::connect::Options {
+                                    version: protocol_version(Some(repo))?,
+                                    #[cfg(feature = "blocking-network-client")]
+                                    ssh: Default::default(),
+                                },
+                            )
+                            .await?
+                        }
+                    };
+
+                commands::fetch::process(fetch_transport, repo, &args.url, &options, &mut fetch)
+                    .await?;
+            }
+
+            if !push.is_empty() {
+                let repo = require_repository(&repo, "push")?;
+
+                let advertisement = match push_advertisement.take() {
+                    Some(advertisement) => advertisement,
+                    None => {
+                        // NOTE: push still uses the v1 protocol so we use that here.
+                        let push_transport = connect(
+                            args.url.clone(),
+                            transport::client::connect::Options {
+                                version: transport::Protocol::V1,
+                                #[cfg(feature = "blocking-network-client")]
+                                ssh: Default::default(),
+                            },
+                        )
+                        .await?;
+
+                        commands::push::handshake(push_transport, authenticate).await?
+                    }
+                };
+
+                commands::push::process(advertisement, repo, &mut push).await?;
+            }
 
             // continue; // Useful to inspect .git directory before it disappears
             break Ok(());
         }
 
-        let input = input.split(' ').collect::<Vec<_>>();
-
         trace!("input: {:#?}", input);
 
-        let input_command = Command::new("git-remote-icp")
-            .multicall(true)
-            .subcommand_required(true);
-
-        let input_command = Commands::augment_subcommands(input_command);
-        let matches = input_command.try_get_matches_from(input)?;
-        let command = Commands::from_arg_matches(&matches)?;
+        let command = match commands::parse(input) {
+            Ok(command) => command,
+            Err(err) if err.is_recoverable() => {
+                trace!("{}", err);
+                println!("unsupported");
+                continue;
+            }
+            Err(err) => break Err(err.into()),
+        };
 
         match command {
-            Commands::Capabilities => {
-                // TODO: buffer and flush
-                Commands::VARIANTS
-                    .iter()
-                    .filter(|command| **command != "capabilities" && **command != "list")
-                    .for_each(|command| println!("{}", command));
-                println!();
+            Commands::Capabilities => commands::capabilities::execute(
+                &capabilities,
+                repo.as_ref().map(|repo| repo.git_dir()),
+            ),
+            Commands::Connect { service } => {
+                let connect_service = capabilities
+                    .connect
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("connect is not supported"))?;
+
+                trace!("connect {}", service);
+
+                let connection = connect_service(&args.url, &service)?;
+                commands::connect::execute(connection)?;
+
+                break Ok(());
+            }
+            Commands::Export => {
+                let backend = capabilities
+                    .import_export
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("export is not supported"))?;
+
+                commands::export::execute(backend.as_mut())?;
+
+                break Ok(());
             }
             Commands::Fetch { hash, name } => {
                 trace!("batch fetch {} {}", hash, name);
                 let _ = fetch.insert((hash, name));
             }
-            Commands::List { variant } => {
-                let mut transport = connect(
-                    args.url.clone(),
-                    transport::client::connect::Options {
-                        version: transport::Protocol::V2,
-                        #[cfg(feature = "blocking-network-client")]
-                        ssh: Default::default(),
-                    },
-                )
-                .await?;
-
-                commands::list::execute(&mut transport, authenticate, &variant).await?
+            Commands::Import { name } => {
+                trace!("batch import {}", name);
+                let _ = import.insert(name);
+            }
+            Commands::List { variant } => match variant {
+                Some(ListVariant::ForPush) => {
+                    // NOTE: push still uses the v1 protocol so we use that here.
+                    let push_transport = connect(
+                        args.url.clone(),
+                        transport::client::connect::Options {
+                            version: transport::Protocol::V1,
+                            #[cfg(feature = "blocking-network-client")]
+                            ssh: Default::default(),
+                        },
+                    )
+                    .await?;
+
+                    let advertisement =
+                        commands::push::handshake(push_transport, authenticate).await?;
+
+                    commands::list::execute_for_push(
+                        &advertisement.refs,
+                        &advertisement.capabilities,
+                        &options,
+                    )?;
+
+                    // Reused by the push batch that follows
+                    push_advertisement = Some(advertisement);
+                }
+                None => {
+                    let mut transport = connect(
+                        args.url.clone(),
+                        transport::client::connect::Options {
+                            version: protocol_version(repo.as_ref())?,
+                            #[cfg(feature = "blocking-network-client")]
+                            ssh: Default::default(),
+                        },
+                    )
+                    .await?;
+
+                    let ref_prefixes = repo
+                        .as_ref()
+                        .map(|repo| commands::list::ref_prefixes(repo, &args.repository))
+                        .unwrap_or_default();
+
+                    let outcome = commands::list::execute(
+                        &mut transport,
+                        authenticate,
+                        &ref_prefixes,
+                        repo.as_ref().map(|repo| repo.object_hash()),
+                        &options,
+                    )
+                    .await?;
+
+                    // Reused by the fetch batch that follows, along with the
+                    // handshake so that it isn't performed twice.
+                    let transport = git::transport::Handshaken::new(transport, outcome);
+
+                    if transport.is_reusable() {
+                        upload_pack_transport = Some(transport);
+                    }
+                }
+            },
+            Commands::Option { name, value } => {
+                commands::option::execute(&mut options, &name, &value.join(" "))
             }
             Commands::Push { src_dst } => {
                 trace!("batch push {}", src_dst);
                 let _ = push.insert(src_dst);
             }
+            Commands::StatelessConnect { service } => {
+                if service == commands::stateless_connect::UPLOAD_PACK {
+                    let transport = connect(
+                        args.url.clone(),
+                        transport::client::connect::Options {
+                            version: transport::Protocol::V2,
+                            #[cfg(feature = "blocking-network-client")]
+                            ssh: Default::default(),
+                        },
+                    )
+                    .await?;
+
+                    match commands::stateless_connect::execute(transport, authenticate).await? {
+                        commands::stateless_connect::Outcome::Fallback => (),
+                        commands::stateless_connect::Outcome::Closed => break Ok(()),
+                    }
+                } else {
+                    commands::stateless_connect::fallback()
+                }
+            }
+        }
+    }
+}
+
+/// Opens the repository that git invoked us from, if any.
+///
+/// `GIT_DIR` is opened directly rather than via its parent since it may be a
+/// bare repository, a `--separate-git-dir` or a linked worktree. It isn't set
+/// when running `git ls-remote` outside of a repository, in which case only
+/// `list` can be used.
+fn open_repository() -> anyhow::Result<Option<gitoxide::Repository>> {
+    match env::var_os(GIT_DIR) {
+        Some(git_dir) => {
+            trace!("GIT_DIR: {:?}", git_dir);
+            let repo = gitoxide::open(git_dir).context("failed to open GIT_DIR")?;
+            Ok(Some(repo))
         }
+        None => {
+            trace!("GIT_DIR is not set");
+            Ok(None)
+        }
+    }
+}
+
+/// The protocol version to ask upload-pack for, according to
+/// `protocol.version`.
+///
+/// Servers that don't support the requested version respond with an older one,
+/// which we detect from the handshake.
+fn protocol_version(repo: Option<&gitoxide::Repository>) -> anyhow::Result<transport::Protocol> {
+    let version = match repo {
+        Some(repo) => git::config::Config::from_repository(repo)?.integer("protocol.version")?,
+        None => None,
+    };
+    trace!("protocol.version: {:?}", version);
+
+    match version {
+        Some(0) | Some(1) => Ok(transport::Protocol::V1),
+        _ => Ok(transport::Protocol::V2),
     }
 }
+
+fn require_repository<'a>(
+    repo: &'a Option<gitoxide::Repository>,
+    command: &str,
+) -> anyhow::Result<&'a gitoxide::Repository> {
+    repo.as_ref()
+        .ok_or_else(|| anyhow!("{} requires a repository but GIT_DIR is not set", command))
+}
@@ -0,0 +1,218 @@
+use anyhow::{anyhow, Context as _};
+use ic_agent::export::Principal;
+use log::trace;
+use std::net::IpAddr;
+use tokio::runtime::Runtime;
+
+#[cfg(test)]
+mod tests;
+
+/// Domains that serve canisters from a subdomain named after the canister ID,
+/// e.g. `<canister-id>.raw.ic0.app`.
+const GATEWAY_DOMAINS: &[&str] = &["ic0.app", "icp0.io", "ic0.localhost", "localhost"];
+
+/// The canister ID embedded in a URL's host, if any.
+///
+/// This is either a subdomain of a known gateway, optionally using the `raw`
+/// subdomain, or the host itself as in `icp://<canister-id>/<path>`.
+pub fn from_host(host: &str) -> Option<Principal> {
+    let host = host.trim_end_matches('.').to_lowercase();
+
+    let label = match host.split_once('.') {
+        Some((label, domain)) => {
+            let domain = domain.strip_prefix("raw.").unwrap_or(domain);
+
+            if !GATEWAY_DOMAINS.contains(&domain) {
+                return None;
+            }
+
+            label
+        }
+        None => host.as_str(),
+    };
+
+    Principal::from_text(label).ok()
+}
+
+/// Resolves the canister that serves a custom domain.
+pub trait Resolver {
+    /// The canister for `domain`, or `None` if it isn't served by one.
+    fn resolve(&self, domain: &str) -> anyhow::Result<Option<Principal>>;
+}
+
+/// The canister ID for a URL's host, either embedded in it or resolved as a
+/// custom domain.
+///
+/// `None` means the host can't identify a canister, like `localhost` or an IP
+/// address, so a configured or default canister ID should be used instead.
+pub fn resolve(host: &str, resolver: &dyn Resolver) -> anyhow::Result<Option<Principal>> {
+    if let Some(canister_id) = from_host(host) {
+        return Ok(Some(canister_id));
+    }
+
+    if !is_custom_domain(host) {
+        return Ok(None);
+    }
+
+    resolver.resolve(host)?.map(Some).ok_or_else(|| {
+        anyhow!(
+            "failed to resolve a canister ID for {}. Set `icp.canisterId = <canister id>`",
+            host
+        )
+    })
+}
+
+fn is_custom_domain(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    host.contains('.') && !host.ends_with(".localhost") && host.parse::<IpAddr>().is_err()
+}
+
+const IC_DOMAINS_PATH: &str = "/.well-known/ic-domains";
+
+/// Resolves custom domains the way boundary nodes do.
+///
+/// The canister ID is read from the `_canister-id.<domain>` TXT record, using
+/// DNS over HTTPS, and the canister must list the domain in its
+/// `/.well-known/ic-domains` as served by the network's gateway.
+pub struct DnsResolver {
+    client: reqwest::Client,
+    dns_query_url: String,
+    gateway_url: String,
+}
+
+/// Used when custom domains can't be resolved because no DNS over HTTPS
+/// endpoint is configured.
+pub struct NoResolver;
+
+impl Resolver for NoResolver {
+    fn resolve(&self, domain: &str) -> anyhow::Result<Option<Principal>> {
+        Err(anyhow!(
+            "{} looks like a custom domain. Set `icp.canisterId = <canister id>`, or `icp.dnsQueryUrl` to resolve it with DNS over HTTPS",
+            domain
+        ))
+    }
+}
+
+/// The URL of `/.well-known/ic-domains` for a canister, served from a
+/// subdomain of the gateway, e.g. `https://<canister-id>.ic0.app` or
+/// `http://<canister-id>.localhost:8000`.
+fn ic_domains_url(gateway_url: &str, canister_id: &Principal) -> anyhow::Result<reqwest::Url> {
+    let mut url = reqwest::Url::parse(gateway_url)
+        .with_context(|| format!("invalid gateway URL {}", gateway_url))?;
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("no host in gateway URL {}", gateway_url))?;
+
+    // IP addresses can't have subdomains, but local replicas also serve
+    // canisters from subdomains of localhost.
+    let host = match host.trim_matches(['[', ']']).parse::<IpAddr>() {
+        Ok(ip) if ip.is_loopback() => "localhost".to_string(),
+        Ok(_) => return Err(anyhow!("no gateway domain in {}", gateway_url)),
+        Err(_) => host.to_string(),
+    };
+
+    url.set_host(Some(&format!("{}.{}", canister_id, host)))?;
+    url.set_path(IC_DOMAINS_PATH);
+    url.set_query(None);
+
+    Ok(url)
+}
+
+impl DnsResolver {
+    pub fn new(dns_query_url: String, gateway_url: String) -> Self {
+        Self {
+            client: reqwest::Client::default(),
+            dns_query_url,
+            gateway_url,
+        }
+    }
+
+    async fn txt_record(&self, name: &str) -> anyhow::Result<Vec<String>> {
+        let response = self
+            .client
+            .get(&self.dns_query_url)
+            .query(&[("name", name), ("type", "TXT")])
+            .header(reqwest::header::ACCEPT, "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+
+        let records = response["Answer"]
+            .as_array()
+            .map(|answers| {
+                answers
+                    .iter()
+                    .filter_map(|answer| answer["data"].as_str())
+                    .map(|data| data.trim_matches('"').to_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(records)
+    }
+
+    async fn ic_domains(&self, canister_id: &Principal) -> anyhow::Result<Vec<String>> {
+        let url = ic_domains_url(&self.gateway_url, canister_id)?;
+
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let domains = response
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        Ok(domains)
+    }
+}
+
+impl Resolver for DnsResolver {
+    fn resolve(&self, domain: &str) -> anyhow::Result<Option<Principal>> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let runtime = Runtime::new()?;
+
+        runtime.block_on(async {
+            let name = format!("_canister-id.{}", domain);
+            let records = self
+                .txt_record(&name)
+                .await
+                .with_context(|| format!("failed to look up {}", name))?;
+            trace!("{}: {:?}", name, records);
+
+            let canister_id = match records.first() {
+                Some(record) => Principal::from_text(record)
+                    .map_err(|err| anyhow!("invalid canister id in {}: {}", name, err))?,
+                None => return Ok(None),
+            };
+
+            let domains = self.ic_domains(&canister_id).await.with_context(|| {
+                format!("failed to read {} for {}", IC_DOMAINS_PATH, canister_id)
+            })?;
+            trace!("{} for {}: {:?}", IC_DOMAINS_PATH, canister_id, domains);
+
+            if !domains.contains(&domain) {
+                return Err(anyhow!(
+                    "canister {} doesn't list {} in {}",
+                    canister_id,
+                    domain,
+                    IC_DOMAINS_PATH
+                ));
+            }
+
+            Ok(Some(canister_id))
+        })
+    }
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+const CANISTER_ID: &str = "w7uni-tiaaa-aaaam-qaydq-cai";
+
+fn canister_id() -> Option<Principal> {
+    Some(Principal::from_text(CANISTER_ID).expect("valid canister id"))
+}
+
+#[test]
+fn test_from_gateway_host() {
+    for host in [
+        "w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app",
+        "w7uni-tiaaa-aaaam-qaydq-cai.ic0.app",
+        "w7uni-tiaaa-aaaam-qaydq-cai.icp0.io",
+        "w7uni-tiaaa-aaaam-qaydq-cai.raw.icp0.io",
+        "w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.localhost",
+        "w7uni-tiaaa-aaaam-qaydq-cai.localhost",
+    ] {
+        assert_eq!(from_host(host), canister_id(), "{}", host);
+    }
+}
+
+#[test]
+fn test_from_bare_host() {
+    assert_eq!(from_host(CANISTER_ID), canister_id(), "host");
+}
+
+#[test]
+fn test_from_other_host() {
+    for host in [
+        "git.codebase.ic0.localhost",
+        "git.codebase.org",
+        "w7uni-tiaaa-aaaam-qaydq-cai.example.com",
+        "localhost",
+    ] {
+        assert_eq!(from_host(host), None, "{}", host);
+    }
+}
+
+struct StaticResolver(Option<Principal>);
+
+impl Resolver for StaticResolver {
+    fn resolve(&self, _domain: &str) -> anyhow::Result<Option<Principal>> {
+        Ok(self.0)
+    }
+}
+
+#[test]
+fn test_resolve_custom_domain() {
+    let result = resolve("git.codebase.org", &StaticResolver(canister_id()));
+    assert_eq!(result.ok(), Some(canister_id()), "custom domain");
+
+    let result = resolve("git.codebase.org", &StaticResolver(None));
+    assert!(result.is_err(), "unresolved custom domain");
+}
+
+#[test]
+fn test_resolve_skips_resolver() {
+    let resolver = StaticResolver(None);
+
+    let result = resolve("w7uni-tiaaa-aaaam-qaydq-cai.icp0.io", &resolver);
+    assert_eq!(result.ok(), Some(canister_id()), "gateway host");
+
+    for host in ["localhost", "127.0.0.1", "git.codebase.ic0.localhost"] {
+        let result = resolve(host, &resolver);
+        assert_eq!(result.ok(), Some(None), "{}", host);
+    }
+}
+
+#[test]
+fn test_ic_domains_url() {
+    let canister_id = canister_id().expect("valid canister id");
+    for (gateway_url, expected) in [
+        (
+            "https://ic0.app",
+            "https://w7uni-tiaaa-aaaam-qaydq-cai.ic0.app/.well-known/ic-domains",
+        ),
+        (
+            "http://localhost:8000/",
+            "http://w7uni-tiaaa-aaaam-qaydq-cai.localhost:8000/.well-known/ic-domains",
+        ),
+        (
+            "http://127.0.0.1:4943",
+            "http://w7uni-tiaaa-aaaam-qaydq-cai.localhost:4943/.well-known/ic-domains",
+        ),
+    ] {
+        let result = ic_domains_url(gateway_url, &canister_id).map(|url| url.to_string());
+        assert_eq!(result.ok().as_deref(), Some(expected), "{}", gateway_url);
+    }
+}
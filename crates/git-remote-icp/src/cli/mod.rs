@@ -0,0 +1,296 @@
+//! Subcommands for managing identities, for when the binary is run directly
+//! rather than by git.
+
+use crate::config;
+use crate::dfx;
+use crate::identity;
+use anyhow::{anyhow, Context as _};
+use clap::{CommandFactory as _, Parser, Subcommand, ValueEnum};
+use git_remote_helper::git::config::{self as git_config, Config, Scope};
+use git_repository as git;
+use ic_agent::Identity as _;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, Ed25519KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use std::env;
+use std::ffi::OsString;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+/// Where identities created or imported with `identity` are stored, relative
+/// to the config directory.
+const IDENTITY_DIR: &str = "git-remote-icp/identity";
+
+#[derive(Debug, Parser)]
+#[command(name = "git-remote-icp", about, version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Manage the identity used to authenticate with canisters
+    #[command(subcommand)]
+    Identity(IdentityCommand),
+
+    /// Print the principal and key type of the configured identity
+    Whoami {
+        /// Use the settings of a remote in the current repository
+        #[arg(long)]
+        remote: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum IdentityCommand {
+    /// Generate a new private key and use it
+    New {
+        name: String,
+
+        #[arg(long, value_enum, default_value_t = NewKeyType::Ed25519)]
+        key_type: NewKeyType,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+
+    /// Copy an existing PEM file and use it
+    Import {
+        name: String,
+
+        pem_file: PathBuf,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+
+    /// Use an identity created with `identity new`, `identity import` or
+    /// `dfx identity new`
+    Use {
+        name: String,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum NewKeyType {
+    Ed25519,
+    Prime256v1,
+}
+
+#[derive(Debug, clap::Args)]
+struct ScopeArgs {
+    /// Write to the current repository's config instead of the global config
+    #[arg(long)]
+    local: bool,
+}
+
+impl ScopeArgs {
+    fn scope(&self) -> Scope {
+        if self.local {
+            Scope::Local
+        } else {
+            Scope::Global
+        }
+    }
+}
+
+/// Whether the binary was run directly rather than by git, which always passes
+/// the name of a remote and its URL, and sets `GIT_DIR`.
+pub fn is_direct(args: impl Iterator<Item = OsString>) -> bool {
+    let args = args.skip(1).collect::<Vec<_>>();
+    is_direct_with(&args, env::var_os("GIT_DIR").is_some())
+}
+
+/// Decides by the arguments themselves so that a remote can have any name,
+/// including that of a subcommand. A subcommand followed by a flag or one of
+/// its own subcommands never looks like a remote and its URL.
+fn is_direct_with(args: &[OsString], git_dir: bool) -> bool {
+    let (first, second) = match args {
+        [first, second] => (first.to_string_lossy(), second.to_string_lossy()),
+        _ => return true,
+    };
+
+    let mut command = Cli::command();
+    command.build();
+
+    let subcommand = match command.find_subcommand(&*first) {
+        Some(subcommand) => subcommand,
+        None => return false,
+    };
+
+    if second.starts_with('-') || subcommand.find_subcommand(&*second).is_some() {
+        return true;
+    }
+
+    !git_dir && !second.contains("://")
+}
+
+pub fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Identity(IdentityCommand::New {
+            name,
+            key_type,
+            scope,
+        }) => {
+            let path = identity_path(&name)?;
+            let pem = generate(key_type)?;
+            write_private_key(&path, &pem)?;
+            eprintln!("Created identity '{}' in {}", name, path.display());
+            use_private_key(&path, scope.scope())
+        }
+        Command::Identity(IdentityCommand::Import {
+            name,
+            pem_file,
+            scope,
+        }) => {
+            if identity::is_encrypted(&pem_file) {
+                return Err(anyhow!(
+                    "encrypted keys can't be imported. Set `icp.privateKey = {}` to use it in place",
+                    pem_file.display()
+                ));
+            }
+
+            let pem = std::fs::read(&pem_file)
+                .with_context(|| format!("failed to read {}", pem_file.display()))?;
+            identity::from_pem(&pem)
+                .with_context(|| format!("failed to load {}", pem_file.display()))?;
+
+            let path = identity_path(&name)?;
+            write_private_key(&path, &pem)?;
+            eprintln!("Imported identity '{}' into {}", name, path.display());
+            use_private_key(&path, scope.scope())
+        }
+        Command::Identity(IdentityCommand::Use { name, scope }) => {
+            let path = identity_path(&name)?;
+
+            if path.is_file() {
+                use_private_key(&path, scope.scope())
+            } else {
+                // Fail early if there's no dfx identity either.
+                dfx::identity_pem(&name)?;
+                // `icp.privateKey` takes precedence so it has to go.
+                git_config::unset(scope.scope(), "icp.privateKey")?;
+                git_config::set(scope.scope(), "icp.identity", &name)?;
+                eprintln!("Using dfx identity '{}'", name);
+                Ok(())
+            }
+        }
+        Command::Whoami { remote } => {
+            // Unlike when run by git, GIT_DIR isn't set.
+            let config = match git::discover(env::current_dir()?) {
+                Ok(repo) => Config::from_repository(&repo)?,
+                Err(_) => Config::open()?,
+            };
+
+            let loaded = match remote {
+                Some(remote) => {
+                    let url = config
+                        .string(&format!("remote.{}.url", remote))
+                        .ok_or_else(|| anyhow!("no such remote '{}'", remote))?;
+                    identity::load(&config::Settings::new(&config, &remote, &url))?
+                }
+                None => identity::load(&config::Settings::global(&config))?,
+            };
+
+            let principal = loaded.identity.sender().map_err(|err| anyhow!(err))?;
+            let key_type = loaded
+                .key_type
+                .map_or_else(|| "anonymous".to_string(), |key_type| key_type.to_string());
+
+            println!("Principal: {}", principal);
+            println!("Key type: {}", key_type);
+            Ok(())
+        }
+    }
+}
+
+fn identity_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(anyhow!("invalid identity name '{}'", name));
+    }
+
+    let config_dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+            PathBuf::from(home).join(".config")
+        }
+    };
+
+    Ok(config_dir.join(IDENTITY_DIR).join(format!("{}.pem", name)))
+}
+
+/// Generates a PKCS#8 private key, which is what dfx creates too.
+fn generate(key_type: NewKeyType) -> anyhow::Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+
+    let pkcs8 = match key_type {
+        NewKeyType::Ed25519 => Ed25519KeyPair::generate_pkcs8(&rng),
+        NewKeyType::Prime256v1 => {
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+        }
+    }
+    .map_err(|_| anyhow!("failed to generate private key"))?;
+
+    let pem = pem::encode(&pem::Pem {
+        tag: "PRIVATE KEY".to_string(),
+        contents: pkcs8.as_ref().to_vec(),
+    });
+
+    Ok(pem.into_bytes())
+}
+
+/// Writes a private key that only the current user can read, refusing to
+/// overwrite an existing one.
+fn write_private_key(path: &Path, pem: &[u8]) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt as _;
+        options.mode(0o600);
+    }
+
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    file.write_all(pem)?;
+
+    Ok(())
+}
+
+fn use_private_key(path: &Path, scope: Scope) -> anyhow::Result<()> {
+    let loaded = identity::from_pem_file(path)?;
+    let principal = loaded.identity.sender().map_err(|err| anyhow!(err))?;
+    let key_type = loaded
+        .key_type
+        .map_or_else(|| "unknown".to_string(), |key_type| key_type.to_string());
+
+    let path = path
+        .to_str()
+        .ok_or_else(|| anyhow!("{} is not valid UTF-8", path.display()))?;
+    // `icp.identity` would be ignored, so it's removed to avoid confusion.
+    git_config::unset(scope, "icp.identity")?;
+    git_config::set(scope, "icp.privateKey", path)?;
+
+    eprintln!(
+        "Set icp.privateKey to {} ({}, principal {})",
+        path, key_type, principal
+    );
+
+    Ok(())
+}
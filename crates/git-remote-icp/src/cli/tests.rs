@@ -0,0 +1,59 @@
+use super::*;
+
+fn args(args: &[&str]) -> Vec<OsString> {
+    args.iter().map(OsString::from).collect()
+}
+
+#[test]
+fn test_is_direct_subcommands() {
+    for input in [
+        &["whoami"][..],
+        &["whoami", "--remote=origin"],
+        &["whoami", "--remote", "origin"],
+        &["identity", "--help"],
+        &["identity", "use"],
+        &["identity", "new", "alice"],
+        &["identity", "new", "alice", "--local"],
+        &["help", "identity"],
+    ] {
+        assert!(is_direct_with(&args(input), false), "{:?}", input);
+    }
+}
+
+#[test]
+fn test_is_direct_mistyped_subcommand() {
+    assert!(
+        is_direct_with(&args(&["whoami", "origin"]), false),
+        "without GIT_DIR"
+    );
+}
+
+#[test]
+fn test_is_direct_run_by_git() {
+    for input in [
+        &[
+            "origin",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul/hello-world.git",
+        ][..],
+        &[
+            "icp://git.codebase.org/@paul/hello-world.git",
+            "icp://git.codebase.org/@paul/hello-world.git",
+        ],
+        &["origin", "http://localhost:8000/@paul/hello-world.git"],
+        &["whoami", "icp://git.codebase.org/@paul/hello-world.git"],
+    ] {
+        assert!(!is_direct_with(&args(input), false), "{:?}", input);
+        assert!(!is_direct_with(&args(input), true), "{:?}", input);
+    }
+}
+
+#[test]
+fn test_is_direct_remote_named_after_subcommand() {
+    assert!(
+        !is_direct_with(
+            &args(&["whoami", "git.codebase.org/@paul/hello-world.git"]),
+            true
+        ),
+        "with GIT_DIR"
+    );
+}
@@ -0,0 +1,327 @@
+//! Settings for the helper, looked up from the following places in order of
+//! precedence:
+//!
+//! 1. The `GIT_REMOTE_ICP_*` environment variables, e.g.
+//!    `GIT_REMOTE_ICP_CANISTER_ID`.
+//! 2. `remote.<name>.icp*` for the remote being used, e.g.
+//!    `remote.origin.icpCanisterId`.
+//! 3. `icp.<url>.*` for the most specific section matching the URL being
+//!    used, e.g. `[icp "icp://git.example.ic0.app"] canisterId`.
+//! 4. `icp.*`, e.g. `icp.canisterId`.
+//! 5. The network selected with `icp.network`, which is looked up the same
+//!    way. See [`network`].
+//! 6. The default, if any.
+
+use crate::dfx;
+use anyhow::anyhow;
+use git_remote_helper::git::config::{self, Config, Scope};
+use ic_agent::export::Principal;
+use std::env;
+use std::path::PathBuf;
+
+mod network;
+
+#[cfg(test)]
+mod tests;
+
+pub use network::Network;
+
+struct Setting {
+    /// The name in the `icp` section and in `icp.<url>` sections.
+    name: &'static str,
+
+    /// The name in `remote.<name>` sections.
+    remote_name: &'static str,
+
+    env: &'static str,
+}
+
+pub struct Settings<'a> {
+    config: &'a Config,
+    remote: Option<String>,
+    url: String,
+}
+
+impl<'a> Settings<'a> {
+    /// `repository` is either the name of a configured remote or a URL, as
+    /// passed to the helper by git.
+    pub fn new(config: &'a Config, repository: &str, url: &str) -> Self {
+        let remote = config
+            .string(&format!("remote.{}.url", repository))
+            .map(|_| repository.to_owned());
+
+        Self {
+            config,
+            remote,
+            url: url.to_owned(),
+        }
+    }
+
+    /// Settings that aren't specific to a remote, as when not running as a
+    /// remote helper.
+    pub fn global(config: &'a Config) -> Self {
+        Self {
+            config,
+            remote: None,
+            url: String::new(),
+        }
+    }
+
+    /// The config keys for a setting, in order of precedence.
+    fn keys(&self, setting: &Setting) -> Vec<String> {
+        let remote = self
+            .remote
+            .iter()
+            .map(|remote| format!("remote.{}.{}", remote, setting.remote_name));
+
+        let urls = Some(&self.url)
+            .filter(|url| !url.is_empty())
+            .map(|url| url_candidates(url))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|url| format!("icp.{}.{}", url, setting.name));
+
+        remote
+            .chain(urls)
+            .chain(std::iter::once(format!("icp.{}", setting.name)))
+            .collect()
+    }
+
+    /// The first config key that's set for a setting.
+    fn key(&self, setting: &Setting) -> Option<String> {
+        self.keys(setting)
+            .into_iter()
+            .find(|key| self.config.string(key).is_some())
+    }
+
+    fn string(&self, setting: &Setting) -> Option<String> {
+        env::var(setting.env)
+            .ok()
+            .or_else(|| self.key(setting).and_then(|key| self.config.string(&key)))
+    }
+
+    fn boolean(&self, setting: &Setting) -> anyhow::Result<Option<bool>> {
+        match env::var(setting.env) {
+            Ok(value) => config::parse_boolean(&value)
+                .map(Some)
+                .ok_or_else(|| anyhow!("bad boolean value '{}' for {}", value, setting.env)),
+            Err(_) => match self.key(setting) {
+                Some(key) => self.config.boolean(&key),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn path(&self, setting: &Setting) -> anyhow::Result<Option<PathBuf>> {
+        match env::var_os(setting.env) {
+            Some(value) => Ok(Some(PathBuf::from(value))),
+            None => match self.key(setting) {
+                Some(key) => self.config.path(&key),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+/// The URLs that `icp.<url>` sections may use to match `url`, from most to
+/// least specific.
+///
+/// Like `http.<url>`, a section matches when its URL is the same as `url` or
+/// a prefix of it that ends at a path component.
+fn url_candidates(url: &str) -> Vec<String> {
+    let mut url = url.trim_end_matches('/');
+
+    let host = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let root = url[host..].find('/').map(|i| host + i).unwrap_or(url.len());
+
+    let mut candidates = Vec::new();
+
+    loop {
+        candidates.push(url.to_owned());
+        candidates.push(format!("{}/", url));
+
+        match url.rfind('/') {
+            Some(i) if url.len() > root => url = &url[..i],
+            _ => break candidates,
+        }
+    }
+}
+
+const CANISTER_ID: Setting = Setting {
+    name: "canisterId",
+    remote_name: "icpCanisterId",
+    env: "GIT_REMOTE_ICP_CANISTER_ID",
+};
+
+/// The canister ID if it's set explicitly. Otherwise it's derived from the
+/// URL, falling back to [`default_canister_id`] for the network.
+pub fn canister_id(settings: &Settings<'_>) -> anyhow::Result<Option<Principal>> {
+    settings
+        .string(&CANISTER_ID)
+        .map(|canister_id| {
+            Principal::from_text(&canister_id)
+                .map_err(|err| anyhow!("invalid canister id '{}': {}", canister_id, err))
+        })
+        .transpose()
+}
+
+/// Records the canister ID resolved for a custom domain in the repository's
+/// config as `icp.<scheme>://<domain>.canisterId`, so that it's used from
+/// then on instead of resolving it again.
+pub fn cache_canister_id(
+    scheme: &str,
+    domain: &str,
+    canister_id: &Principal,
+) -> anyhow::Result<()> {
+    let key = format!("icp.{}://{}.{}", scheme, domain, CANISTER_ID.name);
+    config::set(Scope::Local, &key, &canister_id.to_text())
+}
+
+/// The canister ID of the network's `canisterId`, if it has one. The
+/// `playground` preset and user-defined networks don't have one unless it's
+/// configured.
+pub fn default_canister_id(network: &Network) -> anyhow::Result<Option<Principal>> {
+    network
+        .canister_id
+        .as_deref()
+        .map(|canister_id| {
+            Principal::from_text(canister_id).map_err(|err| {
+                anyhow!(
+                    "invalid canister id '{}' for network '{}': {}",
+                    canister_id,
+                    network.name,
+                    err
+                )
+            })
+        })
+        .transpose()
+}
+
+const DNS_QUERY_URL: Setting = Setting {
+    name: "dnsQueryUrl",
+    remote_name: "icpDnsQueryUrl",
+    env: "GIT_REMOTE_ICP_DNS_QUERY_URL",
+};
+
+/// The DNS over HTTPS endpoint used to resolve custom domains, if any. There's
+/// no default so that DNS queries aren't sent to a third party unless asked
+/// to.
+pub fn dns_query_url(settings: &Settings<'_>) -> Option<String> {
+    settings.string(&DNS_QUERY_URL)
+}
+
+const FETCH_ROOT_KEY: Setting = Setting {
+    name: "fetchRootKey",
+    remote_name: "icpFetchRootKey",
+    env: "GIT_REMOTE_ICP_FETCH_ROOT_KEY",
+};
+
+/// Whether to fetch the root key from the replica instead of using the
+/// hard-coded mainnet one, which is refused for mainnet replicas.
+pub fn fetch_root_key(
+    settings: &Settings<'_>,
+    network: &Network,
+    replica_url: &str,
+) -> anyhow::Result<bool> {
+    let fetch_root_key = settings
+        .boolean(&FETCH_ROOT_KEY)?
+        .unwrap_or(network.fetch_root_key);
+
+    if fetch_root_key && network::is_mainnet(replica_url) {
+        return Err(anyhow!(
+            "refusing to fetch the root key from mainnet replica {}. Unset `icp.fetchRootKey`",
+            replica_url
+        ));
+    }
+
+    Ok(fetch_root_key)
+}
+
+const NETWORK: Setting = Setting {
+    name: "network",
+    remote_name: "icpNetwork",
+    env: "GIT_REMOTE_ICP_NETWORK",
+};
+
+/// The name of the network selected with `icp.network`, which is one of the
+/// presets (`ic`, `local` or `playground`) or defined in an
+/// `[icp-network "<name>"]` section.
+///
+/// Defaults to `local` for `icp://<canister-name>.local/` URLs and `ic`
+/// otherwise.
+pub fn network_name(settings: &Settings<'_>, local_url: bool) -> String {
+    let default = if local_url {
+        network::LOCAL
+    } else {
+        network::DEFAULT
+    };
+
+    settings
+        .string(&NETWORK)
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Whether `name` is the local replica, whose address can be taken from a
+/// dfx project.
+pub fn is_local_network(name: &str) -> bool {
+    name == network::LOCAL
+}
+
+/// The network called `name`. The address of the local replica is taken from
+/// the dfx project, if any.
+pub fn network(
+    settings: &Settings<'_>,
+    name: &str,
+    project: Option<&dfx::Project>,
+) -> anyhow::Result<Network> {
+    let discovered_replica_url = project
+        .filter(|_| is_local_network(name))
+        .and_then(dfx::Project::local_replica_url);
+
+    Network::from_config(settings.config, name, discovered_replica_url)
+}
+
+const IDENTITY: Setting = Setting {
+    name: "identity",
+    remote_name: "icpIdentity",
+    env: "GIT_REMOTE_ICP_IDENTITY",
+};
+
+/// The name of a dfx identity to use when `icp.privateKey` isn't set.
+pub fn identity(settings: &Settings<'_>) -> Option<String> {
+    settings.string(&IDENTITY)
+}
+
+const PRIVATE_KEY: Setting = Setting {
+    name: "privateKey",
+    remote_name: "icpPrivateKey",
+    env: "GIT_REMOTE_ICP_PRIVATE_KEY",
+};
+
+pub fn private_key(settings: &Settings<'_>) -> anyhow::Result<Option<PathBuf>> {
+    settings.path(&PRIVATE_KEY)
+}
+
+const REPLICA_URL: Setting = Setting {
+    name: "replicaUrl",
+    remote_name: "icpReplicaUrl",
+    env: "GIT_REMOTE_ICP_REPLICA_URL",
+};
+
+pub fn replica_url(settings: &Settings<'_>, network: &Network) -> String {
+    settings
+        .string(&REPLICA_URL)
+        .unwrap_or_else(|| network.replica_url.clone())
+}
+
+const VERBOSE: Setting = Setting {
+    name: "verbose",
+    remote_name: "icpVerbose",
+    env: "GIT_REMOTE_ICP_VERBOSE",
+};
+
+/// Whether to report which identity is used, which is off by default to keep
+/// git's output clean.
+pub fn verbose(settings: &Settings<'_>) -> anyhow::Result<bool> {
+    Ok(settings.boolean(&VERBOSE)?.unwrap_or(false))
+}
@@ -0,0 +1,114 @@
+use anyhow::anyhow;
+use git_remote_helper::git::config::Config;
+
+pub const DEFAULT: &str = "ic";
+pub const LOCAL: &str = "local";
+
+/// The section that user-defined networks, or overrides for the presets, are
+/// read from, as in `[icp-network "staging"]`.
+const SECTION: &str = "icp-network";
+
+struct Preset {
+    name: &'static str,
+    replica_url: &'static str,
+    fetch_root_key: bool,
+    canister_id: Option<&'static str>,
+}
+
+const PRESETS: &[Preset] = &[
+    Preset {
+        name: "ic",
+        replica_url: "https://ic0.app",
+        fetch_root_key: false,
+        canister_id: Some("w7uni-tiaaa-aaaam-qaydq-cai"),
+    },
+    Preset {
+        name: LOCAL,
+        replica_url: "http://localhost:8000",
+        fetch_root_key: true,
+        canister_id: Some("rwlgt-iiaaa-aaaaa-aaaaa-cai"),
+    },
+    Preset {
+        // Playground canisters are deployed to mainnet, but don't have a
+        // canister ID we can default to.
+        name: "playground",
+        replica_url: "https://ic0.app",
+        fetch_root_key: false,
+        canister_id: None,
+    },
+];
+
+/// Hosts of mainnet replicas, which must be verified with the hard-coded root
+/// key.
+const MAINNET_HOSTS: &[&str] = &["ic0.app", "icp0.io", "icp-api.io"];
+
+/// Where to find the replica and how to talk to it, bundled under a name.
+#[derive(Debug)]
+pub struct Network {
+    pub name: String,
+    pub replica_url: String,
+    pub fetch_root_key: bool,
+    pub canister_id: Option<String>,
+}
+
+impl Network {
+    /// Reads a network from its `icp-network.<name>.*` settings, on top of
+    /// the preset with the same name if there is one.
+    ///
+    /// `discovered_replica_url` takes precedence over the preset's, as when
+    /// the address of the local replica is known from a dfx project.
+    pub fn from_config(
+        config: &Config,
+        name: &str,
+        discovered_replica_url: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let preset = PRESETS.iter().find(|preset| preset.name == name);
+        let key = |key: &str| format!("{}.{}.{}", SECTION, name, key);
+
+        let replica_url = config
+            .string(&key("replicaUrl"))
+            .or(discovered_replica_url)
+            .or_else(|| preset.map(|preset| preset.replica_url.to_owned()))
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown network '{}'. Set `{}` or use one of: {}",
+                    name,
+                    key("replicaUrl"),
+                    PRESETS
+                        .iter()
+                        .map(|preset| preset.name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })?;
+
+        let fetch_root_key = match config.boolean(&key("fetchRootKey"))? {
+            Some(fetch_root_key) => fetch_root_key,
+            None => preset.map_or(false, |preset| preset.fetch_root_key),
+        };
+
+        let canister_id = config
+            .string(&key("canisterId"))
+            .or_else(|| preset.and_then(|preset| preset.canister_id.map(ToOwned::to_owned)));
+
+        Ok(Self {
+            name: name.to_owned(),
+            replica_url,
+            fetch_root_key,
+            canister_id,
+        })
+    }
+}
+
+/// Whether a replica URL points at mainnet.
+pub fn is_mainnet(replica_url: &str) -> bool {
+    let host = replica_url
+        .split_once("://")
+        .map_or(replica_url, |(_, rest)| rest);
+    let host = host.split(['/', ':']).next().unwrap_or_default();
+    let host = host.trim_end_matches('.').to_lowercase();
+
+    MAINNET_HOSTS
+        .iter()
+        .any(|mainnet| host == *mainnet || host.ends_with(&format!(".{}", mainnet)))
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+#[test]
+fn test_url_candidates() {
+    let result =
+        url_candidates("icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul/hello-world.git");
+    assert_eq!(
+        result,
+        vec![
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul/hello-world.git",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul/hello-world.git/",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/@paul/",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app",
+            "icp://w7uni-tiaaa-aaaam-qaydq-cai.raw.ic0.app/",
+        ],
+        "candidates"
+    );
+}
+
+#[test]
+fn test_url_candidates_without_path() {
+    let result = url_candidates("https://git.example.ic0.app/");
+    assert_eq!(
+        result,
+        vec![
+            "https://git.example.ic0.app",
+            "https://git.example.ic0.app/"
+        ],
+        "candidates"
+    );
+}
+
+#[test]
+fn test_is_mainnet() {
+    for url in [
+        "https://ic0.app",
+        "https://icp0.io/",
+        "https://icp-api.io:443",
+        "https://boundary.ic0.app",
+    ] {
+        assert!(network::is_mainnet(url), "{}", url);
+    }
+
+    for url in [
+        "http://localhost:8000",
+        "http://127.0.0.1:4943",
+        "https://ic0.app.example.com",
+    ] {
+        assert!(!network::is_mainnet(url), "{}", url);
+    }
+}
+
+#[test]
+fn test_url_candidates_documented_section() {
+    // The section used as an example in README.md and .gitconfig, which
+    // matches because git passes the icp:// URL rather than an https:// one.
+    let result = url_candidates("icp://git.example.ic0.app/@paul/hello-world.git");
+    assert!(
+        result.iter().any(|url| url == "icp://git.example.ic0.app"),
+        "candidates"
+    );
+}
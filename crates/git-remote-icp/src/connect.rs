@@ -1,5 +1,8 @@
-use crate::http::Remote;
+use crate::canister;
+use crate::config;
+use crate::http::{Remote, SharedRemote};
 
+use anyhow::anyhow;
 use git::protocol::transport;
 use git::url::Scheme;
 use git_repository as git;
@@ -7,6 +10,7 @@ use ic_agent::agent::http_transport::ReqwestHttpReplicaV2Transport;
 use ic_agent::export::Principal;
 use ic_agent::{Agent, Identity};
 use log::trace;
+use std::cell::RefCell;
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use transport::client::connect::Error;
@@ -15,7 +19,10 @@ pub fn connect<'a, Url, E>(
     identity: Arc<dyn Identity>,
     fetch_root_key: bool,
     replica_url: String,
-    canister_id: Principal,
+    canister_id: Option<Principal>,
+    default_canister_id: Option<Principal>,
+    network_name: String,
+    resolver: Box<dyn canister::Resolver>,
 ) -> impl Fn(Url, transport::connect::Options) -> Result<Box<dyn transport::client::Transport + Send + 'a>, Error>
 where
     Url: TryInto<git::url::Url, Error = E>,
@@ -24,7 +31,11 @@ where
     trace!("identity: {:#?}", identity);
     trace!("fetch_root_key: {:#?}", fetch_root_key);
     trace!("replica_url: {}", replica_url);
-    trace!("canister_id: {}", canister_id);
+    trace!("canister_id: {:?}", canister_id);
+    trace!("default_canister_id: {:?}", default_canister_id);
+    trace!("network_name: {}", network_name);
+
+    let remote_cache: RefCell<Option<SharedRemote>> = RefCell::new(None);
 
     move |url: Url, options| {
         let mut url = url.try_into().map_err(git::url::parse::Error::from)?;
@@ -38,6 +49,9 @@ where
 
         trace!("Provided URL scheme: {:#?}", url.scheme);
 
+        // Kept to cache resolved canister IDs under the URL git knows about.
+        let provided_scheme = url.scheme.as_str().to_owned();
+
         url.scheme = match url.scheme {
             Scheme::Ext(scheme) if &scheme == "icp" => Ok(Scheme::Https),
             scheme @ (Scheme::Https | Scheme::Http) => Ok(scheme),
@@ -46,24 +60,33 @@ where
 
         trace!("Resolved URL scheme: {:#?}", url.scheme);
 
-        let replica_transport = ReqwestHttpReplicaV2Transport::create(&replica_url)
-            .map_err(|err| Error::Connection(Box::new(err)))?;
+        // The remote is shared by every connection in a session so that we
+        // only resolve the canister, create the agent, fetch the root key and
+        // start the worker thread once.
+        let cached_remote = remote_cache.borrow().clone();
 
-        let agent = Agent::builder()
-            .with_transport(replica_transport)
-            .with_arc_identity(identity.clone())
-            .build()
-            .map_err(|err| Error::Connection(Box::new(err)))?;
+        let remote = match cached_remote {
+            Some(remote) => remote,
+            None => {
+                let canister_id = match canister_id {
+                    Some(canister_id) => canister_id,
+                    None => resolve_canister_id(
+                        &url,
+                        &provided_scheme,
+                        default_canister_id,
+                        &network_name,
+                        resolver.as_ref(),
+                    )?,
+                };
 
-        if fetch_root_key {
-            let runtime = Runtime::new().map_err(|err| Error::Connection(Box::new(err)))?;
+                trace!("Resolved canister ID: {}", canister_id);
 
-            runtime
-                .block_on(agent.fetch_root_key())
-                .map_err(|err| Error::Connection(Box::new(err)))?;
-        }
-
-        let remote = Remote::new(agent, canister_id);
+                let agent = build_agent(identity.clone(), fetch_root_key, &replica_url)?;
+                let remote = SharedRemote::new(Remote::new(agent, canister_id));
+                *remote_cache.borrow_mut() = Some(remote.clone());
+                remote
+            }
+        };
 
         let transport = transport::client::http::connect_http(
             remote,
@@ -74,3 +97,74 @@ where
         Ok(Box::new(transport))
     }
 }
+
+fn resolve_canister_id(
+    url: &git::url::Url,
+    provided_scheme: &str,
+    default_canister_id: Option<Principal>,
+    network_name: &str,
+    resolver: &dyn canister::Resolver,
+) -> Result<Principal, Error> {
+    // Used when the URL doesn't identify a canister.
+    let default = || {
+        default_canister_id.ok_or_else(|| {
+            Error::Connection(
+                anyhow!(
+                    "no canister id for network '{}'; set icp.canisterId",
+                    network_name
+                )
+                .into(),
+            )
+        })
+    };
+
+    let host = match url.host() {
+        Some(host) => host,
+        None => return default(),
+    };
+
+    if let Some(canister_id) = canister::from_host(host) {
+        trace!("Canister ID from URL: {}", canister_id);
+        return Ok(canister_id);
+    }
+
+    match canister::resolve(host, resolver).map_err(|err| Error::Connection(err.into()))? {
+        Some(canister_id) => {
+            trace!("Canister ID resolved for {}: {}", host, canister_id);
+
+            if let Err(err) = config::cache_canister_id(provided_scheme, host, &canister_id) {
+                trace!("failed to cache canister ID: {}", err);
+            }
+
+            Ok(canister_id)
+        }
+        None => default(),
+    }
+}
+
+fn build_agent(
+    identity: Arc<dyn Identity>,
+    fetch_root_key: bool,
+    replica_url: &str,
+) -> Result<Agent, Error> {
+    trace!("building agent");
+
+    let replica_transport = ReqwestHttpReplicaV2Transport::create(replica_url)
+        .map_err(|err| Error::Connection(Box::new(err)))?;
+
+    let agent = Agent::builder()
+        .with_transport(replica_transport)
+        .with_arc_identity(identity)
+        .build()
+        .map_err(|err| Error::Connection(Box::new(err)))?;
+
+    if fetch_root_key {
+        let runtime = Runtime::new().map_err(|err| Error::Connection(Box::new(err)))?;
+
+        runtime
+            .block_on(agent.fetch_root_key())
+            .map_err(|err| Error::Connection(Box::new(err)))?;
+    }
+
+    Ok(agent)
+}
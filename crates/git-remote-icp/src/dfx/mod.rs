@@ -0,0 +1,143 @@
+//! Discovery of dfx projects, so that canisters deployed to a local replica
+//! can be used without configuring their IDs and the replica's address.
+
+use anyhow::{anyhow, Context as _};
+use ic_agent::export::Principal;
+use log::trace;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+
+#[cfg(test)]
+mod tests;
+
+const DFX_JSON: &str = "dfx.json";
+const IDENTITY_DIR: &str = ".config/dfx/identity";
+const IDENTITY_PEM: &str = "identity.pem";
+const IDENTITY_PEM_ENCRYPTED: &str = "identity.pem.encrypted";
+const LOCAL_CANISTER_IDS_JSON: &str = ".dfx/local/canister_ids.json";
+const NETWORKS_JSON: &str = ".config/dfx/networks.json";
+
+/// The TLD used in `icp://<canister-name>.local/` URLs.
+const LOCAL_TLD: &str = ".local";
+
+pub struct Project {
+    pub root: PathBuf,
+
+    /// The address of the local replica, from `dfx.json` or the user's
+    /// `networks.json`.
+    pub local_bind: Option<String>,
+
+    /// The IDs of canisters deployed to the local replica, by name.
+    pub local_canister_ids: BTreeMap<String, String>,
+}
+
+impl Project {
+    /// Finds the dfx project containing `dir`, if any.
+    pub fn discover(dir: &Path) -> anyhow::Result<Option<Self>> {
+        let root = match dir.ancestors().find(|dir| dir.join(DFX_JSON).is_file()) {
+            Some(root) => root.to_owned(),
+            None => return Ok(None),
+        };
+
+        trace!("dfx project: {:?}", root);
+
+        let dfx_json = read_json(&root.join(DFX_JSON))?.unwrap_or_default();
+
+        let local_bind = match local_bind(&dfx_json) {
+            Some(bind) => Some(bind),
+            None => match env::var_os("HOME") {
+                Some(home) => read_json(&PathBuf::from(home).join(NETWORKS_JSON))?
+                    .and_then(|networks| networks["local"]["bind"].as_str().map(ToOwned::to_owned)),
+                None => None,
+            },
+        };
+
+        let canister_ids = read_json(&root.join(LOCAL_CANISTER_IDS_JSON))?.unwrap_or_default();
+
+        Ok(Some(Self {
+            root,
+            local_bind,
+            local_canister_ids: local_canister_ids(&canister_ids),
+        }))
+    }
+
+    pub fn local_replica_url(&self) -> Option<String> {
+        self.local_bind
+            .as_ref()
+            .map(|bind| format!("http://{}", bind))
+    }
+
+    pub fn local_canister_id(&self, name: &str) -> anyhow::Result<Principal> {
+        let canister_id = self.local_canister_ids.get(name).ok_or_else(|| {
+            anyhow!(
+                "canister '{}' hasn't been deployed to the local replica of the dfx project in {}",
+                name,
+                self.root.display()
+            )
+        })?;
+
+        Principal::from_text(canister_id).map_err(|err| {
+            anyhow!(
+                "invalid canister id '{}' for '{}': {}",
+                canister_id,
+                name,
+                err
+            )
+        })
+    }
+}
+
+/// The path of the PEM file for an identity created with `dfx identity new`,
+/// which is encrypted unless it was created with `--storage-mode plaintext`.
+pub fn identity_pem(name: &str) -> anyhow::Result<PathBuf> {
+    let home = env::var_os("HOME").ok_or_else(|| anyhow!("HOME is not set"))?;
+    let dir = PathBuf::from(home).join(IDENTITY_DIR).join(name);
+
+    [IDENTITY_PEM, IDENTITY_PEM_ENCRYPTED]
+        .iter()
+        .map(|file_name| dir.join(file_name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| anyhow!("dfx identity '{}' not found in {}", name, dir.display()))
+}
+
+/// The canister name in an `icp://<canister-name>.local/` URL's host.
+pub fn local_canister_name(host: &str) -> Option<&str> {
+    host.strip_suffix(LOCAL_TLD)
+        .filter(|name| !name.is_empty() && !name.contains('.'))
+}
+
+fn read_json(path: &Path) -> anyhow::Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let value = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    Ok(Some(value))
+}
+
+fn local_bind(dfx_json: &Value) -> Option<String> {
+    dfx_json["networks"]["local"]["bind"]
+        .as_str()
+        .map(ToOwned::to_owned)
+}
+
+fn local_canister_ids(canister_ids: &Value) -> BTreeMap<String, String> {
+    canister_ids
+        .as_object()
+        .map(|canisters| {
+            canisters
+                .iter()
+                .filter_map(|(name, networks)| {
+                    let canister_id = networks["local"].as_str()?;
+                    Some((name.clone(), canister_id.to_owned()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
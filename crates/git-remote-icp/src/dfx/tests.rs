@@ -0,0 +1,38 @@
+use super::*;
+
+#[test]
+fn test_local_canister_name() {
+    assert_eq!(local_canister_name("assets.local"), Some("assets"), "name");
+    assert_eq!(local_canister_name("git.codebase.local"), None, "subdomain");
+    assert_eq!(local_canister_name(".local"), None, "empty");
+    assert_eq!(local_canister_name("assets.ic0.app"), None, "other");
+}
+
+#[test]
+fn test_local_bind() {
+    let dfx_json = serde_json::json!({
+        "canisters": {},
+        "networks": {
+            "local": {
+                "bind": "127.0.0.1:8000",
+                "type": "ephemeral"
+            }
+        }
+    });
+    assert_eq!(local_bind(&dfx_json), Some("127.0.0.1:8000".into()), "bind");
+    assert_eq!(local_bind(&serde_json::json!({})), None, "no networks");
+}
+
+#[test]
+fn test_local_canister_ids() {
+    let canister_ids = serde_json::json!({
+        "assets": { "local": "rwlgt-iiaaa-aaaaa-aaaaa-cai" },
+        "backend": { "ic": "w7uni-tiaaa-aaaam-qaydq-cai" }
+    });
+    let result = local_canister_ids(&canister_ids);
+    assert_eq!(
+        result,
+        BTreeMap::from([("assets".into(), "rwlgt-iiaaa-aaaaa-aaaaa-cai".into())]),
+        "canister ids"
+    );
+}
@@ -1,6 +1,6 @@
 mod reqwest;
 
-pub use self::reqwest::Remote;
+pub use self::reqwest::{Remote, SharedRemote};
 
 use git_repository as git;
 pub use git::protocol::transport::client::http::*;
@@ -1,5 +1,6 @@
 use ic_agent::export::Principal;
 use ic_agent::Agent;
+use std::sync::{Arc, Mutex};
 
 /// An implementation for HTTP requests via `reqwest`.
 pub struct Remote {
@@ -13,5 +14,10 @@ pub struct Remote {
     response: std::sync::mpsc::Receiver<remote::Response>,
 }
 
+/// A [`Remote`] shared by the transports of a session so that they reuse its
+/// worker thread and runtime.
+#[derive(Clone)]
+pub struct SharedRemote(Arc<Mutex<Remote>>);
+
 ///
 mod remote;
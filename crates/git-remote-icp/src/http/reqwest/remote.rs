@@ -1,7 +1,7 @@
 // Based on
 // https://github.com/Byron/gitoxide/blob/e6b9906c486b11057936da16ed6e0ec450a0fb83/git-transport/src/client/blocking_io/http/reqwest/remote.rs
 
-use crate::{http, http::reqwest::Remote};
+use crate::{http, http::reqwest::Remote, http::reqwest::SharedRemote, http::Http as _};
 
 use candid::{Decode, Encode};
 use git_features::io::pipe;
@@ -15,6 +15,7 @@ use serde_bytes::ByteBuf;
 use std::any::Any;
 use std::io::{Read, Write};
 use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
 use tokio::runtime::Runtime;
 
 /// The error returned by the 'remote' helper, a purely internal construct to perform http requests.
@@ -293,6 +294,49 @@ impl http::Http for Remote {
     }
 }
 
+impl SharedRemote {
+    pub fn new(remote: Remote) -> Self {
+        Self(Arc::new(Mutex::new(remote)))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Remote> {
+        self.0.lock().expect("no panic while holding the lock")
+    }
+}
+
+impl http::Http for SharedRemote {
+    type Headers = pipe::Reader;
+    type ResponseBody = pipe::Reader;
+    type PostBody = pipe::Writer;
+
+    fn get(
+        &mut self,
+        url: &str,
+        base_url: &str,
+        headers: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<http::GetResponse<Self::Headers, Self::ResponseBody>, http::Error> {
+        self.lock().get(url, base_url, headers)
+    }
+
+    fn post(
+        &mut self,
+        url: &str,
+        base_url: &str,
+        headers: impl IntoIterator<Item = impl AsRef<str>>,
+        post_body_kind: PostBodyDataKind,
+    ) -> Result<http::PostResponse<Self::Headers, Self::ResponseBody, Self::PostBody>, http::Error>
+    {
+        self.lock().post(url, base_url, headers, post_body_kind)
+    }
+
+    fn configure(
+        &mut self,
+        config: &dyn Any,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        self.lock().configure(config)
+    }
+}
+
 pub(crate) struct Request {
     pub url: String,
     pub headers: Vec<HeaderField>,
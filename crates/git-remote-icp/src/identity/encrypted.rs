@@ -0,0 +1,123 @@
+//! Decrypting PEM files encrypted by dfx, which stores them as
+//! `identity.pem.encrypted` alongside the parameters in `identity.json`.
+//!
+//! The key is derived from a passphrase with Argon2id and the PEM is
+//! encrypted with AES-256-GCM.
+
+use super::passphrase;
+use anyhow::{anyhow, Context as _};
+use argon2::{Algorithm, Argon2, Params, Version};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM};
+use serde_json::Value;
+use std::path::Path;
+
+pub const EXTENSION: &str = "encrypted";
+
+const IDENTITY_JSON: &str = "identity.json";
+
+// The parameters used by dfx.
+const ARGON2_MEMORY_KIB: u32 = 64000;
+const ARGON2_ITERATIONS: u32 = 3;
+const ARGON2_PARALLELISM: u32 = 1;
+const KEY_LEN: usize = 32;
+
+pub(super) struct Encryption {
+    pub(super) salt: Vec<u8>,
+    pub(super) nonce: Vec<u8>,
+}
+
+/// Reads and decrypts an encrypted PEM file, prompting for its passphrase.
+///
+/// The decrypted PEM is only ever kept in memory.
+pub fn read(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let encryption = encryption(&dir.join(IDENTITY_JSON))?;
+
+    let ciphertext = std::fs::read(path)
+        .with_context(|| format!("failed to read private key {}", path.display()))?;
+
+    let passphrase = passphrase::get(path, &identity_name(path))?;
+
+    match decrypt(&ciphertext, &encryption, passphrase.value()) {
+        Ok(pem) => {
+            passphrase.approve();
+            Ok(pem)
+        }
+        Err(err) => {
+            passphrase.reject();
+            Err(err)
+        }
+    }
+}
+
+/// The name of the dfx identity stored at `path`, which is the name of its
+/// directory, or the file name for keys stored elsewhere.
+pub(super) fn identity_name(path: &Path) -> String {
+    let file_name = path.file_name().unwrap_or_default();
+
+    let name = if file_name == "identity.pem.encrypted" {
+        path.parent().and_then(Path::file_name).unwrap_or(file_name)
+    } else {
+        file_name
+    };
+
+    name.to_string_lossy().into_owned()
+}
+
+fn encryption(path: &Path) -> anyhow::Result<Encryption> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let config: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+
+    let encryption = &config["encryption"];
+
+    let salt = encryption["pw_salt"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing encryption.pw_salt in {}", path.display()))?;
+    // A `SaltString` from the password-hash crate, which is unpadded base64.
+    let salt = base64::decode_config(salt, base64::STANDARD_NO_PAD)
+        .with_context(|| format!("invalid encryption.pw_salt in {}", path.display()))?;
+
+    let nonce = encryption["file_nonce"]
+        .as_array()
+        .and_then(|nonce| {
+            nonce
+                .iter()
+                .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| anyhow!("missing encryption.file_nonce in {}", path.display()))?;
+
+    Ok(Encryption { salt, nonce })
+}
+
+pub(super) fn decrypt(
+    ciphertext: &[u8],
+    encryption: &Encryption,
+    passphrase: &str,
+) -> anyhow::Result<Vec<u8>> {
+    let params = Params::new(
+        ARGON2_MEMORY_KIB,
+        ARGON2_ITERATIONS,
+        ARGON2_PARALLELISM,
+        Some(KEY_LEN),
+    )
+    .map_err(|err| anyhow!("invalid key derivation parameters: {}", err))?;
+
+    let mut key = [0; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), &encryption.salt, &mut key)
+        .map_err(|err| anyhow!("failed to derive key: {}", err))?;
+
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| anyhow!("invalid key"))?;
+    let nonce = Nonce::try_assume_unique_for_key(&encryption.nonce)
+        .map_err(|_| anyhow!("invalid nonce"))?;
+
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = LessSafeKey::new(key)
+        .open_in_place(nonce, Aad::empty(), &mut buffer)
+        .map_err(|_| anyhow!("failed to decrypt private key. Is the passphrase correct?"))?;
+
+    Ok(plaintext.to_vec())
+}
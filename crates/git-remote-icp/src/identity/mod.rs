@@ -0,0 +1,161 @@
+//! Loading identities from PEM files, detecting the type of key they contain.
+
+use crate::config;
+use crate::dfx;
+use anyhow::{anyhow, Context as _};
+use ic_agent::identity::{AnonymousIdentity, BasicIdentity, Secp256k1Identity};
+use ic_agent::Identity;
+use log::trace;
+use std::path::Path;
+use std::sync::Arc;
+
+mod encrypted;
+mod passphrase;
+mod prime256v1;
+
+#[cfg(test)]
+mod tests;
+
+pub use prime256v1::Prime256v1Identity;
+
+/// The name dfx uses for the anonymous identity, which has no PEM file.
+pub const ANONYMOUS: &str = "anonymous";
+
+// DER-encoded object identifiers, including their tag and length.
+const ED25519_OID: &[u8] = &[0x06, 0x03, 0x2b, 0x65, 0x70];
+const PRIME256V1_OID: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const SECP256K1_OID: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+
+const EC_PRIVATE_KEY: &str = "EC PRIVATE KEY";
+const PRIVATE_KEY: &str = "PRIVATE KEY";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyType {
+    Ed25519,
+    Prime256v1,
+    Secp256k1,
+}
+
+impl std::fmt::Display for KeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Ed25519 => "ed25519",
+            Self::Prime256v1 => "prime256v1",
+            Self::Secp256k1 => "secp256k1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An identity along with the type of key it was loaded from, if any.
+pub struct LoadedIdentity {
+    pub identity: Arc<dyn Identity>,
+    pub key_type: Option<KeyType>,
+}
+
+impl LoadedIdentity {
+    pub fn anonymous() -> Self {
+        Self {
+            identity: Arc::new(AnonymousIdentity {}),
+            key_type: None,
+        }
+    }
+}
+
+/// Loads the identity configured with `icp.privateKey` or `icp.identity`, in
+/// that order, falling back to the anonymous identity.
+pub fn load(settings: &config::Settings<'_>) -> anyhow::Result<LoadedIdentity> {
+    let private_key_path = config::private_key(settings)?;
+    trace!("private key path: {:#?}", private_key_path);
+
+    if let Some(path) = private_key_path {
+        trace!("Using identity for private key found in git config");
+        return from_pem_file(&path);
+    }
+
+    match config::identity(settings) {
+        Some(name) if name == ANONYMOUS => {
+            trace!("Using anonymous identity");
+            Ok(LoadedIdentity::anonymous())
+        }
+        Some(name) => {
+            trace!("Using dfx identity {}", name);
+            from_pem_file(&dfx::identity_pem(&name)?)
+        }
+        None => {
+            trace!("No private key found in git config, using anonymous identity");
+            Ok(LoadedIdentity::anonymous())
+        }
+    }
+}
+
+/// Loads an identity from a PEM file, decrypting it first if it has been
+/// encrypted by dfx.
+pub fn from_pem_file(path: &Path) -> anyhow::Result<LoadedIdentity> {
+    let pem = if is_encrypted(path) {
+        encrypted::read(path)?
+    } else {
+        std::fs::read(path)
+            .with_context(|| format!("failed to read private key {}", path.display()))?
+    };
+
+    from_pem(&pem).with_context(|| format!("failed to load private key {}", path.display()))
+}
+
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == encrypted::EXTENSION)
+}
+
+pub fn from_pem(pem: &[u8]) -> anyhow::Result<LoadedIdentity> {
+    let private_key = private_key(pem)?;
+    let key_type = key_type(&private_key)?;
+    trace!("key type: {}", key_type);
+
+    let identity: Arc<dyn Identity> = match key_type {
+        KeyType::Ed25519 => Arc::new(BasicIdentity::from_pem(pem).map_err(|err| anyhow!(err))?),
+        KeyType::Prime256v1 => Arc::new(Prime256v1Identity::from_private_key(&private_key)?),
+        KeyType::Secp256k1 => {
+            Arc::new(Secp256k1Identity::from_pem(pem).map_err(|err| anyhow!(err))?)
+        }
+    };
+
+    Ok(LoadedIdentity {
+        identity,
+        key_type: Some(key_type),
+    })
+}
+
+/// The private key in a PEM file, skipping other blocks like the
+/// `EC PARAMETERS` that OpenSSL writes by default.
+fn private_key(pem: &[u8]) -> anyhow::Result<pem::Pem> {
+    let blocks = pem::parse_many(pem).map_err(|err| anyhow!("invalid PEM file: {}", err))?;
+
+    blocks
+        .into_iter()
+        .find(|block| block.tag == PRIVATE_KEY || block.tag == EC_PRIVATE_KEY)
+        .ok_or_else(|| anyhow!("no private key found in PEM file"))
+}
+
+/// Detects the type of a private key from the algorithm or curve identifier
+/// it contains.
+fn key_type(private_key: &pem::Pem) -> anyhow::Result<KeyType> {
+    let contains = |oid: &[u8]| {
+        private_key
+            .contents
+            .windows(oid.len())
+            .any(|window| window == oid)
+    };
+
+    if contains(ED25519_OID) {
+        Ok(KeyType::Ed25519)
+    } else if contains(PRIME256V1_OID) {
+        Ok(KeyType::Prime256v1)
+    } else if contains(SECP256K1_OID) {
+        Ok(KeyType::Secp256k1)
+    } else {
+        Err(anyhow!(
+            "unsupported private key. Use an ed25519, prime256v1 or secp256k1 key"
+        ))
+    }
+}
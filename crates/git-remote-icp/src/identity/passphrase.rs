@@ -0,0 +1,171 @@
+//! Getting passphrases the way git gets passwords, so that the user's askpass
+//! program or credential helper is used.
+
+use anyhow::{anyhow, Context as _};
+use git_remote_helper::git::config::Config;
+use log::trace;
+use std::env;
+use std::ffi::OsString;
+use std::io::Write as _;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const GIT_ASKPASS: &str = "GIT_ASKPASS";
+const SSH_ASKPASS: &str = "SSH_ASKPASS";
+
+/// Passphrases are only stored with `git credential approve` if this is set,
+/// since helpers like `store` keep them in plain text.
+const STORE_PASSPHRASE: &str = "icp.storePassphrase";
+
+// Credentials are described as `file://<path>` so that helpers can store a
+// passphrase per key.
+const CREDENTIAL_PROTOCOL: &str = "file";
+
+enum Source {
+    Askpass,
+    Credential { store: bool },
+}
+
+pub struct Passphrase {
+    path: String,
+    username: String,
+    value: String,
+    source: Source,
+}
+
+impl Passphrase {
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Lets the credential helper store a passphrase that worked, if
+    /// `icp.storePassphrase` is set.
+    pub fn approve(&self) {
+        if let Source::Credential { store: true } = self.source {
+            self.report("approve")
+        }
+    }
+
+    /// Lets the credential helper forget a passphrase that didn't work.
+    pub fn reject(&self) {
+        if let Source::Credential { .. } = self.source {
+            self.report("reject")
+        }
+    }
+
+    fn report(&self, action: &str) {
+        let input = format!(
+            "{}password={}\n\n",
+            credential_input(&self.path, &self.username),
+            self.value
+        );
+        if let Err(err) = credential(action, &input) {
+            trace!("git credential {} failed: {}", action, err);
+        }
+    }
+}
+
+/// Gets the passphrase for the identity `name` whose key is at `path`, using
+/// `GIT_ASKPASS`, `core.askPass` or `SSH_ASKPASS` if set, like git does, and
+/// `git credential fill` otherwise.
+pub fn get(path: &Path, name: &str) -> anyhow::Result<Passphrase> {
+    let path = path.display().to_string();
+    let username = name.to_owned();
+    let config = Config::open()?;
+
+    if let Some(askpass) = askpass(&config)? {
+        trace!("askpass: {:?}", askpass);
+
+        let prompt = format!("Passphrase for {}: ", path);
+        let output = Command::new(&askpass)
+            .arg(prompt)
+            .stdin(Stdio::null())
+            .stderr(Stdio::inherit())
+            .output()
+            .with_context(|| format!("failed to run {:?}", askpass))?;
+
+        if !output.status.success() {
+            return Err(anyhow!("{:?} failed to get a passphrase", askpass));
+        }
+
+        let value = String::from_utf8(output.stdout)?;
+        let value = value.trim_end_matches(&['\r', '\n'][..]).to_owned();
+
+        return Ok(Passphrase {
+            path,
+            username,
+            value,
+            source: Source::Askpass,
+        });
+    }
+
+    let output = credential("fill", &format!("{}\n", credential_input(&path, &username)))?;
+
+    let value = output
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .ok_or_else(|| anyhow!("no passphrase given for {}", path))?
+        .to_owned();
+
+    let store = config.boolean(STORE_PASSPHRASE)?.unwrap_or(false);
+
+    Ok(Passphrase {
+        path,
+        username,
+        value,
+        source: Source::Credential { store },
+    })
+}
+
+fn askpass(config: &Config) -> anyhow::Result<Option<OsString>> {
+    let non_empty = |askpass: &OsString| !askpass.is_empty();
+
+    if let Some(askpass) = env::var_os(GIT_ASKPASS).filter(non_empty) {
+        return Ok(Some(askpass));
+    }
+
+    if let Some(askpass) = config.path("core.askPass")? {
+        let askpass = askpass.into_os_string();
+        if non_empty(&askpass) {
+            return Ok(Some(askpass));
+        }
+    }
+
+    Ok(env::var_os(SSH_ASKPASS).filter(non_empty))
+}
+
+fn credential_input(path: &str, username: &str) -> String {
+    format!(
+        "protocol={}\npath={}\nusername={}\n",
+        CREDENTIAL_PROTOCOL, path, username
+    )
+}
+
+/// Runs `git credential <action>`, which may prompt on the terminal.
+///
+/// Our own stdin and stdout are used to talk to git, so the child gets its
+/// own pipes.
+fn credential(action: &str, input: &str) -> anyhow::Result<String> {
+    let mut child = Command::new("git")
+        .arg("credential")
+        .arg(action)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("failed to run git credential {}", action))?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("failed to write to git credential {}", action))?
+        .write_all(input.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!("git credential {} failed", action));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
@@ -0,0 +1,93 @@
+use anyhow::anyhow;
+use ic_agent::export::Principal;
+use ic_agent::identity::Signature;
+use ic_agent::Identity;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair as _, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+/// The DER encoding of a prime256v1 public key's algorithm identifier, which
+/// is followed by the uncompressed point.
+const DER_PUBLIC_KEY_PREFIX: &[u8] = &[
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// The PKCS#8 algorithm identifier for a prime256v1 key.
+const ALGORITHM_IDENTIFIER: &[u8] = &[
+    0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48,
+    0xce, 0x3d, 0x03, 0x01, 0x07,
+];
+
+/// An identity for a prime256v1 (P-256) key, which ic-agent doesn't provide
+/// yet.
+#[derive(Debug)]
+pub struct Prime256v1Identity {
+    key_pair: EcdsaKeyPair,
+    der_encoded_public_key: Vec<u8>,
+}
+
+impl Prime256v1Identity {
+    /// Loads a key from either a PKCS#8 `PRIVATE KEY` or a SEC1
+    /// `EC PRIVATE KEY`, as written by `openssl ecparam -genkey`.
+    pub fn from_private_key(private_key: &pem::Pem) -> anyhow::Result<Self> {
+        let pkcs8 = if private_key.tag == "EC PRIVATE KEY" {
+            sec1_to_pkcs8(&private_key.contents)
+        } else {
+            private_key.contents.clone()
+        };
+
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8)
+            .map_err(|err| anyhow!("invalid prime256v1 private key: {}", err))?;
+
+        let der_encoded_public_key =
+            [DER_PUBLIC_KEY_PREFIX, key_pair.public_key().as_ref()].concat();
+
+        Ok(Self {
+            key_pair,
+            der_encoded_public_key,
+        })
+    }
+}
+
+impl Identity for Prime256v1Identity {
+    fn sender(&self) -> Result<Principal, String> {
+        Ok(Principal::self_authenticating(&self.der_encoded_public_key))
+    }
+
+    fn sign(&self, blob: &[u8]) -> Result<Signature, String> {
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), blob)
+            .map_err(|err| format!("failed to sign with prime256v1 key: {}", err))?;
+
+        Ok(Signature {
+            public_key: Some(self.der_encoded_public_key.clone()),
+            signature: Some(signature.as_ref().to_vec()),
+        })
+    }
+}
+
+/// Wraps a SEC1 `ECPrivateKey` in a PKCS#8 `PrivateKeyInfo`, which is what
+/// ring expects.
+fn sec1_to_pkcs8(sec1: &[u8]) -> Vec<u8> {
+    let version = der(0x02, &[0x00]);
+    let private_key = der(0x04, sec1);
+    der(
+        0x30,
+        &[&version, ALGORITHM_IDENTIFIER, &private_key].concat(),
+    )
+}
+
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let len = contents.len();
+
+    let len = if len < 0x80 {
+        vec![len as u8]
+    } else if len <= 0xff {
+        vec![0x81, len as u8]
+    } else {
+        vec![0x82, (len >> 8) as u8, len as u8]
+    };
+
+    [&[tag], len.as_slice(), contents].concat()
+}
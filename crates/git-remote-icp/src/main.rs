@@ -1,51 +1,96 @@
+mod canister;
+mod cli;
 mod config;
 mod connect;
+mod dfx;
 mod http;
+mod identity;
 
 use anyhow::anyhow;
-use ic_agent::identity::{AnonymousIdentity, Identity, Secp256k1Identity};
+use clap::Parser as _;
+use git_remote_helper::cli::Args;
+use git_remote_helper::git::config::Config;
+use git_repository as git;
+use ic_agent::Identity as _;
+use identity::LoadedIdentity;
 use log::trace;
-use std::sync::Arc;
+use std::env;
 
 pub fn main() -> anyhow::Result<()> {
     env_logger::init();
 
-    let private_key_path = config::private_key();
-    trace!("private key path: {:#?}", private_key_path);
+    if cli::is_direct(env::args_os()) {
+        return cli::main();
+    }
+
+    let args = Args::parse();
+    let config = Config::open()?;
+    let settings = config::Settings::new(&config, &args.repository, &args.url);
 
-    let identity = get_identity(private_key_path)?;
+    let LoadedIdentity { identity, key_type } = identity::load(&settings)?;
+    trace!("key type: {:?}", key_type);
 
     let principal = identity.sender().map_err(|err| anyhow!(err))?;
     trace!("principal: {}", principal);
-    eprintln!("Principal for caller: {}", principal);
 
-    let fetch_root_key = config::fetch_root_key();
-    trace!("fetch root key: {}", fetch_root_key);
+    if config::verbose(&settings)? {
+        eprintln!("Principal for caller: {}", principal);
+    }
+
+    let url = git::url::parse(args.url.as_str().into())?;
+    let local_canister_name = url.host().and_then(dfx::local_canister_name);
+    trace!("local canister name: {:?}", local_canister_name);
 
-    let replica_url = config::replica_url();
+    let network_name = config::network_name(&settings, local_canister_name.is_some());
+
+    // A dfx project only describes the local replica, so there's no need to
+    // read it, or fail on its files, when talking to any other network.
+    let project = if local_canister_name.is_some() || config::is_local_network(&network_name) {
+        dfx::Project::discover(&env::current_dir()?)?
+    } else {
+        None
+    };
+
+    let network = config::network(&settings, &network_name, project.as_ref())?;
+    trace!("network: {:#?}", network);
+
+    let replica_url = config::replica_url(&settings, &network);
     trace!("replica url: {}", replica_url);
 
-    let canister_id = config::canister_id()?;
-    trace!("canister id: {}", canister_id);
+    let fetch_root_key = config::fetch_root_key(&settings, &network, &replica_url)?;
+    trace!("fetch root key: {}", fetch_root_key);
+
+    let local_canister_id = match local_canister_name {
+        Some(name) => {
+            let project = project.as_ref().ok_or_else(|| {
+                anyhow!("icp://{}.local/ URLs must be used inside a dfx project", name)
+            })?;
+            Some(project.local_canister_id(name)?)
+        }
+        None => None,
+    };
+
+    let canister_id = config::canister_id(&settings)?.or(local_canister_id);
+    trace!("canister id: {:?}", canister_id);
+
+    let default_canister_id = config::default_canister_id(&network)?;
+    trace!("default canister id: {:?}", default_canister_id);
+
+    let resolver: Box<dyn canister::Resolver> = match config::dns_query_url(&settings) {
+        Some(dns_query_url) => Box::new(canister::DnsResolver::new(
+            dns_query_url,
+            replica_url.clone(),
+        )),
+        None => Box::new(canister::NoResolver),
+    };
 
     git_remote_helper::main(connect::connect(
         identity,
         fetch_root_key,
         replica_url,
         canister_id,
+        default_canister_id,
+        network.name,
+        resolver,
     ))
 }
-
-fn get_identity(private_key_path: anyhow::Result<String>) -> anyhow::Result<Arc<dyn Identity>> {
-    match private_key_path {
-        Ok(path) => {
-            eprintln!("Using identity for private key found in git config");
-            let identity = Secp256k1Identity::from_pem_file(path)?;
-            Ok(Arc::new(identity))
-        }
-        Err(_) => {
-            eprintln!("No private key found git config, using anonymous identity");
-            Ok(Arc::new(AnonymousIdentity {}))
-        }
-    }
-}
@@ -0,0 +1,92 @@
+use anyhow::anyhow;
+use git::protocol::transport;
+use git::bstr::BStr;
+use git::url::Scheme;
+use git_remote_helper::Connection;
+use git_repository as git;
+use log::trace;
+use std::convert::{Infallible, TryInto};
+use std::io::Write as _;
+use std::net::TcpStream;
+use transport::client::connect::Error;
+
+#[cfg(test)]
+mod tests;
+
+const DEFAULT_PORT: u16 = 9418;
+const GIT_PROTOCOL: &str = "GIT_PROTOCOL";
+
+pub async fn connect<Url, E>(
+    url: Url,
+    options: transport::connect::Options,
+) -> Result<Box<dyn transport::client::Transport + Send>, Error>
+where
+    Url: TryInto<git::url::Url, Error = E>,
+    git::url::parse::Error: From<E>,
+{
+    let mut url: git::Url = url.try_into().map_err(git::url::parse::Error::from)?;
+    trace!("Provided URL scheme: {:#?}", url.scheme);
+
+    url.scheme = match url.scheme {
+        Scheme::Ext(scheme) if &scheme == "tcp" => Ok(Scheme::Git),
+        scheme @ Scheme::Git => Ok(scheme),
+        _ => Err(Error::UnsupportedScheme(url.scheme)),
+    }?;
+    trace!("Resolved URL scheme: {:#?}", url.scheme);
+
+    transport::connect::<_, Infallible>(url, options).await
+}
+
+/// Opens a full-duplex connection to `service` for the `connect` capability.
+///
+/// https://git-scm.com/docs/pack-protocol#_git_transport
+pub fn connect_service(url: &str, service: &str) -> anyhow::Result<Connection> {
+    let url = git::url::parse(url.as_bytes().into())?;
+    trace!("connect_service url: {:#?}", url);
+
+    let host = url
+        .host()
+        .ok_or_else(|| anyhow!("missing host in URL: {}", url.to_bstring()))?;
+
+    let port = url.port.unwrap_or(DEFAULT_PORT);
+
+    let host_parameter = match url.port {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    let git_protocol = std::env::var(GIT_PROTOCOL).ok();
+    let request = request(service, &url.path, &host_parameter, git_protocol.as_deref());
+
+    trace!("connect_service request: {:?}", request);
+
+    let stream = TcpStream::connect((host, port))?;
+    let mut writer = stream.try_clone()?;
+
+    writer.write_all(request.as_bytes())?;
+    writer.flush()?;
+
+    Ok(Connection {
+        reader: Box::new(stream),
+        writer: Box::new(writer),
+    })
+}
+
+/// The packet line that asks the daemon for `service`.
+///
+/// Git passes the parameters it wants to send, e.g. `version=2`, separated by
+/// `:`. They follow the host after an extra NUL and are each terminated by
+/// one, like `git_connect` writes them.
+fn request(service: &str, path: &BStr, host: &str, git_protocol: Option<&str>) -> String {
+    let mut request = format!("{} {}\0host={}\0", service, path, host);
+
+    if let Some(git_protocol) = git_protocol.filter(|value| !value.is_empty()) {
+        request.push('\0');
+        for parameter in git_protocol.split(':').filter(|p| !p.is_empty()) {
+            request.push_str(parameter);
+            request.push('\0');
+        }
+    }
+
+    format!("{:04x}{}", request.len() + 4, request)
+}
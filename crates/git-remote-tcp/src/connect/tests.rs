@@ -0,0 +1,20 @@
+use super::*;
+
+#[test]
+fn test_request() {
+    assert_eq!(
+        request("git-upload-pack", "/repo.git".into(), "example.com", None),
+        "002fgit-upload-pack /repo.git\0host=example.com\0",
+        "without parameters"
+    );
+    assert_eq!(
+        request(
+            "git-upload-pack",
+            "/repo.git".into(),
+            "example.com:9418",
+            Some("version=2:object-format=sha1"),
+        ),
+        "0052git-upload-pack /repo.git\0host=example.com:9418\0\0version=2\0object-format=sha1\0",
+        "with parameters"
+    );
+}
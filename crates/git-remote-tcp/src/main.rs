@@ -1,10 +1,17 @@
 mod connect;
 
-use git_remote_helper;
-use connect::connect;
+use connect::{connect, connect_service};
+use git_remote_helper::Capabilities;
 
 #[tokio::main]
 pub async fn main() -> anyhow::Result<()> {
     env_logger::init();
-    git_remote_helper::main(connect).await
+    git_remote_helper::main_with_capabilities(
+        connect,
+        Capabilities {
+            connect: Some(Box::new(connect_service)),
+            ..Default::default()
+        },
+    )
+    .await
 }